@@ -7,11 +7,45 @@ use std::path::PathBuf;
     version = env!("CARGO_PKG_VERSION")
 )]
 pub struct Args {
+    /// Config file to use; `-` reads it from stdin
     #[arg(short, long, value_name = "FILE")]
     pub config: Option<PathBuf>,
 
-    #[arg(short, long, action)]
-    pub verbose: bool,
+    /// Raise log verbosity (-v = debug, -vv = trace).
+    /// Precedence: CLI flag > STASIS_LOG env var > config `log_level`
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Fork into the background and write a PID file to $XDG_RUNTIME_DIR/stasis.pid
+    /// (or a private fallback dir when it is unset)
+    #[arg(long, conflicts_with = "foreground")]
+    pub daemon: bool,
+
+    /// Stay attached to the terminal (default; use under systemd)
+    #[arg(long, alias = "no-daemon")]
+    pub foreground: bool,
+
+    /// Stop an already running instance (SIGTERM) and take over
+    #[arg(long)]
+    pub replace: bool,
+
+    /// Debug: suspend with a short `rtcwake -m mem -s SECS` (default 10) instead
+    /// of the configured command, to test pre-suspend and resume hooks
+    #[arg(long, value_name = "SECS", num_args = 0..=1, default_missing_value = "10")]
+    pub debug_soft_suspend: Option<u64>,
+
+    /// Run the instant (timeout 0) actions, or the named ACTION, then exit
+    /// without starting the daemon
+    #[arg(long, value_name = "ACTION", num_args = 0..=1, default_missing_value = "")]
+    pub once: Option<String>,
+
+    /// Print the config file that would be used and exit
+    #[arg(long)]
+    pub print_config_path: bool,
+
+    /// Print a commented config with every supported key and exit
+    #[arg(long)]
+    pub print_default_config: bool,
 
     #[command(subcommand)]
     pub command: Option<Command>
@@ -22,9 +56,18 @@ pub enum Command {
     #[command(about = "Reload the configuration without restarting Stasis")]
     Reload,
     
-    #[command(about = "Pause all timers")]
-    Pause,
+    #[command(about = "Pause all timers, indefinitely or for a duration")]
+    Pause {
+        #[arg(help = "How long to pause, e.g. 90 (seconds), 30m or 1h30m; omit to pause until resumed")]
+        duration: Option<String>,
+    },
     
+    #[command(about = "Push all pending idle actions back without pausing the timers")]
+    Snooze {
+        #[arg(help = "How long to snooze, e.g. 90 (seconds), 30m or 1h30m; repeating extends it")]
+        duration: String,
+    },
+
     #[command(about = "Resume timers after a pause")]
     Resume,
 
@@ -37,13 +80,55 @@ pub enum Command {
         step: String,
     },
 
+    #[command(about = "Run an action's command, wait for it and show its exit status and stderr")]
+    TestAction {
+        #[arg(help = "Action name, as for 'trigger'")]
+        name: String,
+    },
+
+    #[command(about = "Re-run instant (timeout 0) actions without reloading the config")]
+    ResetInstants,
+
+    #[command(about = "Restore brightness and run resume commands without resetting idle timers")]
+    UndoAll,
+
+    #[command(about = "Skip one kind of action (e.g. 'suspend') while the others keep running")]
+    Inhibit {
+        #[arg(help = "Action kind: brightness, dpms, lock_screen, suspend or custom")]
+        kind: String,
+    },
+
+    #[command(about = "Stop skipping a kind of action after 'inhibit'")]
+    Uninhibit {
+        #[arg(help = "Action kind: brightness, dpms, lock_screen, suspend or custom")]
+        kind: String,
+    },
+
+    #[command(about = "Switch to a named profile from the config, or back with 'none'")]
+    SetProfile {
+        #[arg(help = "Profile name, or 'none' for the power-based actions")]
+        name: String,
+    },
+
+    #[command(about = "Lock the screen and suspend immediately, ignoring timers and pauses", alias = "lock-suspend-now")]
+    Panic,
+
+    #[command(about = "Let the compositor handle idle (timeout actions stand down) or take it back")]
+    CompositorManaged {
+        #[arg(help = "on or off")]
+        state: String,
+    },
+
+    #[command(about = "Check the daemon is responsive: prints uptime and the idle loop's last tick")]
+    Ping,
+
     #[command(about = "Toggle manual idle inhibition (for status bars such as Waybar)")]
     ToggleInhibit,
 
     #[command(about = "Stop the currently running instances of Stasis")]
     Stop,
 
-    #[command(about = "Display durrent session information")]
+    #[command(about = "Display durrent session information", alias = "status")]
     Info {
         #[arg(long, help = "Output as JSON (for Waybar or scripts)")]
         json: bool,
@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// Failure modes of `load_config`, so embedders can tell a missing config
+/// apart from a broken one
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file does not exist
+    NotFound(String),
+    /// The file exists but is not valid Rune
+    Parse { path: String, message: String },
+    /// A key is present but its value is unusable
+    InvalidValue { key: String, message: String },
+    /// The config parsed, but defines no idle actions
+    NoActions,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NotFound(path) => write!(f, "config file '{}' not found", path),
+            ConfigError::Parse { path, message } => {
+                write!(f, "failed to load Rune config from '{}': {}", path, message)
+            }
+            ConfigError::InvalidValue { key, message } => {
+                write!(f, "invalid value for '{}': {}", key, message)
+            }
+            ConfigError::NoActions => write!(f, "no valid idle actions found in config"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -1,12 +1,19 @@
 use std::{collections::BTreeSet, time::Duration};
-use crate::{config::model::StasisConfig, core::utils};
+use crate::{config::model::{AppInhibitPattern, StasisConfig}, core::{manager::state::{ActionMetrics, InhibitReason}, utils}};
 
 impl StasisConfig {
+    #[allow(clippy::too_many_arguments)]
     pub fn pretty_print(
         &self,
         idle_time: Option<Duration>,
         uptime: Option<Duration>,
         is_inhibited: Option<bool>,
+        inhibit_reason: Option<&InhibitReason>,
+        inhibit_remaining: Option<Duration>,
+        idle_for: Option<Duration>,
+        next_action: Option<Duration>,
+        snooze_remaining: Option<Duration>,
+        metrics: Option<&ActionMetrics>,
     ) -> String {
         let mut out = String::new();
 
@@ -16,6 +23,23 @@ impl StasisConfig {
             "  PreSuspendCommand  = {}\n",
             self.pre_suspend_command.as_deref().unwrap_or("-")
         ));
+        out.push_str(&format!("  PreSuspendSettleMs = {}\n", self.pre_suspend_settle_ms));
+        out.push_str(&format!(
+            "  OnLockCommand      = {}\n",
+            self.on_lock_command.as_deref().unwrap_or("-")
+        ));
+        out.push_str(&format!(
+            "  OnUnlockCommand    = {}\n",
+            self.on_unlock_command.as_deref().unwrap_or("-")
+        ));
+        out.push_str(&format!(
+            "  ToAcCommand        = {}\n",
+            self.on_power_change.to_ac_command.as_deref().unwrap_or("-")
+        ));
+        out.push_str(&format!(
+            "  ToBatteryCommand   = {}\n",
+            self.on_power_change.to_battery_command.as_deref().unwrap_or("-")
+        ));
         out.push_str(&format!(
             "  MonitorMedia       = {}\n",
             if self.monitor_media { "true" } else { "false" }
@@ -25,20 +49,98 @@ impl StasisConfig {
             "  RespectInhibitors  = {}\n",
             if self.respect_wayland_inhibitors { "true" } else { "false" }
         ));
-        out.push_str(&format!("  DebounceSeconds    = {}\n", self.debounce_seconds));
+        out.push_str(&format!("  Debounce           = {:?}\n", self.debounce));
+        out.push_str(&format!("  InstantOnStartup   = {}\n", self.trigger_instant_on_startup));
         out.push_str(&format!("  LidCloseAction     = {}\n", self.lid_close_action));
         out.push_str(&format!("  LidOpenAction      = {}\n", self.lid_open_action));
+        out.push_str(&format!(
+            "  LogLevel           = {}\n",
+            self.log_level.map(|l| l.to_string()).unwrap_or_else(|| "-".to_string())
+        ));
+        out.push_str(&format!(
+            "  MetricsListen      = {}\n",
+            self.metrics_listen.as_deref().unwrap_or("-")
+        ));
 
-        let apps = if self.inhibit_apps.is_empty() {
+        let patterns = |list: &[AppInhibitPattern]| if list.is_empty() {
             "-".to_string()
         } else {
-            self.inhibit_apps
+            list.iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        out.push_str(&format!("  InhibitApps        = {}\n", patterns(&self.inhibit_apps)));
+        out.push_str(&format!("  InhibitAppIds      = {}\n", patterns(&self.inhibit_app_ids)));
+        out.push_str(&format!("  InhibitTitles      = {}\n", patterns(&self.inhibit_titles)));
+        out.push_str(&format!("  InhibitFullscreen  = {}\n", self.inhibit_fullscreen));
+
+        let devices = if self.ignore_input_devices.is_empty() {
+            "-".to_string()
+        } else {
+            self.ignore_input_devices.join(",")
+        };
+        out.push_str(&format!("  IgnoreInputDevices = {}\n", devices));
+        out.push_str(&format!("  MinMovement        = {}\n", self.min_activity_movement));
+        out.push_str(&format!("  ActionDispatch     = {}\n", self.action_dispatch));
+        out.push_str(&format!("  RelockGraceSecs    = {}\n", self.relock_grace_seconds));
+        let players = if self.ignored_players.is_empty() {
+            "-".to_string()
+        } else {
+            self.ignored_players
                 .iter()
                 .map(|p| p.to_string())
                 .collect::<Vec<_>>()
                 .join(",")
         };
-        out.push_str(&format!("  InhibitApps        = {}\n", apps));
+        out.push_str(&format!("  IgnoredPlayers     = {}\n", players));
+        out.push_str(&format!(
+            "  LockThenSuspend    = {}\n",
+            self.lock_then_suspend_after.map(utils::format_duration).unwrap_or_else(|| "-".to_string())
+        ));
+        out.push_str(&format!("  ManageBrightness   = {}\n", self.manage_brightness));
+        out.push_str(&format!("  ResetOnMediaStop   = {}\n", self.reset_idle_on_media_stop));
+        out.push_str(&format!("  NetworkActivity    = {}\n", self.network_activity_inhibit));
+        out.push_str(&format!("  NetworkThreshold   = {} kbps\n", self.network_activity_threshold_kbps));
+        out.push_str(&format!(
+            "  InhibitAboveLoad   = {}\n",
+            self.inhibit_above_load.map(|l| l.to_string()).unwrap_or_else(|| "-".to_string())
+        ));
+        let processes = if self.inhibit_processes.is_empty() {
+            "-".to_string()
+        } else {
+            self.inhibit_processes.join(",")
+        };
+        out.push_str(&format!("  InhibitProcesses   = {}\n", processes));
+        out.push_str(&format!("  ActionShell        = {}\n", self.action_shell));
+        out.push_str(&format!("  ActivityDebounceMs = {}\n", self.activity_debounce_ms));
+        out.push_str(&format!(
+            "  LockCheckCommand   = {}\n",
+            self.lock_check_command.as_deref().unwrap_or("-")
+        ));
+        out.push_str(&format!(
+            "  EventLog           = {}\n",
+            self.event_log.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "-".to_string())
+        ));
+        out.push_str(&format!("  SuspendIfSessions  = {}\n", self.suspend_if_sessions));
+        out.push_str(&format!(
+            "  MaxInhibitSeconds  = {}\n",
+            self.max_inhibit_seconds.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string())
+        ));
+        match &self.battery_critical {
+            Some(c) => out.push_str(&format!(
+                "  BatteryCritical    = below {}% (re-arm at {}%): {}\n",
+                c.percent, c.rearm_percent, c.command
+            )),
+            None => out.push_str("  BatteryCritical    = -\n"),
+        }
+        out.push_str(&format!("  LockBeforeSuspend  = {}\n", self.lock_before_suspend));
+        out.push_str(&format!("  BrightnessBackend  = {}\n", self.brightness_backend));
+        out.push_str(&format!("  SuspendCountdown   = {}s\n", self.suspend_countdown_seconds));
+        out.push_str(&format!("  RestoreOnError     = {}\n", self.restore_on_error));
+        out.push_str(&format!("  ResumeSequential   = {}\n", self.resume_sequential));
+        out.push_str(&format!("  PowerDebounceSecs  = {}\n", self.power_debounce_seconds));
+        out.push_str(&format!("  SkipDisplayLocked  = {}\n", self.skip_display_while_locked));
 
         if let Some(idle) = idle_time {
             out.push_str(&format!("  IdleTime           = {}\n", utils::format_duration(idle)));
@@ -46,26 +148,55 @@ impl StasisConfig {
         if let Some(up) = uptime {
             out.push_str(&format!("  Uptime             = {}\n", utils::format_duration(up)));
         }
+        match idle_for {
+            Some(d) => out.push_str(&format!("  Idle               = yes, for {}\n", utils::format_duration(d))),
+            None => out.push_str("  Idle               = no\n"),
+        }
         if let Some(inhibited) = is_inhibited {
             out.push_str(&format!("  IdleInhibited      = {}\n", inhibited));
         }
+        if let Some(reason) = inhibit_reason {
+            out.push_str(&format!("  InhibitReason      = {}\n", reason));
+            if *reason == InhibitReason::Manual {
+                let remaining = inhibit_remaining
+                    .map(|d| format!("{} remaining", utils::format_duration(d)))
+                    .unwrap_or_else(|| "indefinite".to_string());
+                out.push_str(&format!("  InhibitRemaining   = {}\n", remaining));
+            }
+        }
+        if let Some(d) = next_action {
+            out.push_str(&format!("  NextActionIn       = {}\n", utils::format_duration(d)));
+        }
+        if let Some(d) = snooze_remaining {
+            out.push_str(&format!("  Snoozed            = {} remaining\n", utils::format_duration(d)));
+        }
+
+        if let Some(metrics) = metrics {
+            out.push_str("\nMetrics:\n");
+            out.push_str(&format!("  IdleCycles         = {}\n", metrics.idle_cycles));
+            for (kind, count) in &metrics.fired {
+                out.push_str(&format!("  {:<18} = {}\n", format!("Fired.{}", kind), count));
+            }
+        }
 
         // Actions
         out.push_str("\nActions:\n");
 
         // Track groups in order of first occurrence
         let mut seen_groups = BTreeSet::new();
-        for action in &self.actions {
+        for action in self.actions.iter().chain(self.profiles.values().flatten()).chain(&self.manual_actions) {
             let group = if action.name.starts_with("ac.") {
-                "AC"
+                "AC".to_string()
             } else if action.name.starts_with("battery.") {
-                "Battery"
+                "Battery".to_string()
+            } else if let Some(rest) = action.name.strip_prefix("profile.") {
+                format!("Profile {}", rest.split('.').next().unwrap_or_default())
             } else {
-                "Desktop"
+                "Desktop".to_string()
             };
 
             // Print group header only once
-            if seen_groups.insert(group) {
+            if seen_groups.insert(group.clone()) {
                 out.push_str(&format!("  [{}]\n", group));
             }
 
@@ -77,8 +208,20 @@ impl StasisConfig {
                 action.command
             ));
 
-            if let Some(resume_cmd) = &action.resume_command {
-                out.push_str(&format!(" ResumeCommand=\"{}\"", resume_cmd));
+            if action.has_resume_command() {
+                out.push_str(&format!(" ResumeCommand=\"{}\"", action.resume_commands.join("\" then \"")));
+            }
+
+            if action.manual_only {
+                out.push_str(" ManualOnly");
+            }
+
+            if let Some(dir) = &action.working_directory {
+                out.push_str(&format!(" WorkingDirectory=\"{}\"", dir.display()));
+            }
+
+            if let Some(window) = &action.window {
+                out.push_str(&format!(" Active={}", window));
             }
 
             out.push('\n');
@@ -0,0 +1,81 @@
+/// One top-level setting under `stasis:`, as written to the generated template
+pub struct ConfigKey {
+    pub key: &'static str,
+    pub value: &'static str,
+    pub comment: &'static str,
+    /// Written commented out when the key has no default (the value is an example)
+    pub enabled: bool,
+}
+
+const fn key(key: &'static str, value: &'static str, comment: &'static str) -> ConfigKey {
+    ConfigKey { key, value, comment, enabled: true }
+}
+
+const fn example(key: &'static str, value: &'static str, comment: &'static str) -> ConfigKey {
+    ConfigKey { key, value, comment, enabled: false }
+}
+
+/// Every top-level setting read by `load_config`, with its default. The parser
+/// recognizes settings from this table, so a key missing here is reported as
+/// unknown (and taken for an action on desktops).
+pub const GENERAL_KEYS: &[ConfigKey] = &[
+    example("pre_suspend_command", "\"loginctl lock-session\"", "run before every suspend"),
+    key("pre_suspend_settle_ms", "700", "wait after pre_suspend_command before suspending (0 disables)"),
+    example("on_lock_command", "\"playerctl pause\"", "run whenever the session locks"),
+    example("on_unlock_command", "\"playerctl play\"", "run whenever the session unlocks"),
+    key("monitor_media", "true", "pause idle timers while media is playing"),
+    key("ignore_remote_media", "true", "don't let remote players (KDE Connect, Chromecast...) inhibit idle"),
+    example("ignored_players", "[\"mpd\"]", "extra players treated as remote; r\"...\" entries are regexes"),
+    key("reset_idle_on_media_stop", "false", "restart the countdown when playback stops"),
+    key("respect_wayland_inhibitors", "true", "honor idle inhibitors set by Wayland clients"),
+    example("inhibit_apps", "[\"mpv\" r\"steam_app_.*\"]", "pause idle timers while these apps run"),
+    example("inhibit_app_ids", "[\"mpv\"]", "like inhibit_apps, but only matched against window app_ids"),
+    example("inhibit_titles", "[\"YouTube\"]", "like inhibit_apps, but only matched against window titles"),
+    key("inhibit_fullscreen", "false", "pause idle timers while the focused window is fullscreen (Hyprland)"),
+    key("debounce_seconds", "3", "ignore activity this long after resuming from idle (or e.g. \"500ms\")"),
+    key("trigger_instant_on_startup", "true", "fire instant (timeout 0) actions at startup"),
+    key("lid_close_action", "\"ignore\"", "ignore | lock-screen | suspend | \"custom:<command>\""),
+    key("lid_open_action", "\"ignore\"", "ignore | wake | \"custom:<command>\""),
+    example("log_level", "\"info\"", "info | debug | trace (STASIS_LOG and -v/-vv take precedence)"),
+    example("metrics_listen", "\"127.0.0.1:9101\"", "serve Prometheus metrics on this address"),
+    example("ignore_input_devices", "[\"Touchpad\"]", "input devices (name substrings) that never reset idle"),
+    key("min_activity_movement", "0", "pointer travel needed before motion counts as activity (0 disables)"),
+    key("action_dispatch", "\"sequential\"", "how `trigger all` runs actions: sequential | parallel"),
+    key("relock_grace_seconds", "0", "don't re-lock within this long of an unlock (0 disables)"),
+    example("lock_then_suspend_after", "\"30m\"", "suspend once locked for this long"),
+    key("manage_brightness", "true", "capture and restore brightness around brightness actions"),
+    key("network_activity_inhibit", "false", "count network traffic above the threshold as activity (headless)"),
+    key("network_activity_threshold_kbps", "100", "receive + transmit rate (kbit/s) that counts as activity"),
+    example("inhibit_above_load", "4.0", "inhibit idle while the 1-minute load average is above this"),
+    example("inhibit_processes", "[\"make\" \"rsync\"]", "inhibit idle while any of these processes runs"),
+    key("inhibit_process_interval", "5", "seconds between inhibit_processes scans"),
+    key("action_shell", "\"sh\"", "shell running commands, or \"none\" to exec them directly"),
+    key("activity_debounce_ms", "0", "coalesce activity events this close together (0 disables)"),
+    example("lock_check_command", "\"pgrep -x swaylock\"", "skip locking when this succeeds (already locked)"),
+    key("suspend_if_sessions", "true", "false skips suspend while a remote (SSH) session is open"),
+    example("max_inhibit_seconds", "14400", "override media/app/inhibitor pauses lasting longer than this"),
+    key("lock_before_suspend", "false", "lock (and wait for the locker) before any suspend"),
+    key("brightness_backend", "\"auto\"", "auto | sysfs | brightnessctl | none (capture/restore method)"),
+    example("suspend_countdown_seconds", "10", "notify before an idle suspend; activity cancels it"),
+    key("restore_on_error", "true", "restore brightness and the display if an action fails unexpectedly"),
+    key("resume_sequential", "false", "run resume commands one at a time, newest action first"),
+    key("power_debounce_seconds", "0", "wait for the power source to settle this long before switching blocks"),
+    key("skip_display_while_locked", "false", "don't dim or blank the screen while it is locked"),
+    example("event_log", "\"~/.cache/stasis/events.jsonl\"", "JSON line per action, pause, power change and lock"),
+    example("actions_order", "[\"dpms\" \"lock_screen\" \"suspend\"]", "explicit action order; unlisted actions follow by timeout"),
+];
+
+/// Top-level keys that hold blocks or per-action settings rather than a value
+const BLOCK_KEYS: &[&str] = &[
+    "resume_command", "on_power_change", "profile", "templates", "chain", "battery",
+];
+
+/// Whether `key` (snake_case or kebab-case) is a setting or settings block
+/// rather than the name of an action
+pub fn is_special_key(key: &str) -> bool {
+    let snake = key.replace('-', "_");
+    // `debounce` is the short spelling of `debounce_seconds`
+    snake == "debounce"
+        || BLOCK_KEYS.contains(&snake.as_str())
+        || GENERAL_KEYS.iter().any(|k| k.key == snake)
+}
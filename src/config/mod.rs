@@ -1,21 +1,26 @@
 use std::{path::PathBuf};
 use eyre::Result;
 
+pub mod error;
 pub mod info;
+pub mod keys;
 pub mod model;
 pub mod parser;
+pub mod template;
+
+/// The per-user config location, `$XDG_CONFIG_HOME/stasis/stasis.rune`
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("stasis/stasis.rune"))
+}
+
+/// First existing config: the per-user one, then /etc/stasis/stasis.rune
+pub fn find_config_path() -> Option<PathBuf> {
+    user_config_path()
+        .filter(|p| p.exists())
+        .or_else(|| Some(PathBuf::from("/etc/stasis/stasis.rune")).filter(|p| p.exists()))
+}
 
 /// Determine default config path
 pub async fn get_config_path() -> Result<PathBuf> {
-    if let Some(mut path) = dirs::home_dir() {
-        path.push(".config/stasis/stasis.rune");
-        if path.exists() {
-            return Ok(path);
-        }
-    }
-    let fallback = PathBuf::from("/etc/stasis/stasis.rune");
-    if fallback.exists() {
-        return Ok(fallback);
-    }
-    Err(eyre::eyre!("Could not find stasis configuration file"))
+    find_config_path().ok_or_else(|| eyre::eyre!("Could not find stasis configuration file"))
 }
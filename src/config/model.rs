@@ -1,6 +1,9 @@
-use std::{fmt::{Display, Formatter, Result}, time::Instant};
+use std::{collections::BTreeMap, fmt::{Display, Formatter, Result}, path::PathBuf, time::{Duration, Instant}};
+use chrono::NaiveTime;
 use regex::Regex;
 
+use crate::{core::{manager::actions::CommandOptions, utils::session_type}, log::LogLevel};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum IdleAction {
     Brightness,
@@ -10,6 +13,20 @@ pub enum IdleAction {
     Custom,
 }
 
+impl IdleAction {
+    /// Parse a kind name as written in the config or over IPC
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "lock_screen" | "lock-screen" => Some(IdleAction::LockScreen),
+            "suspend" => Some(IdleAction::Suspend),
+            "dpms" => Some(IdleAction::Dpms),
+            "brightness" => Some(IdleAction::Brightness),
+            "custom" => Some(IdleAction::Custom),
+            _ => None,
+        }
+    }
+}
+
 impl Display for IdleAction {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
@@ -28,7 +45,32 @@ pub struct IdleActionBlock {
     pub timeout: u64,
     pub command: String,
     pub kind: IdleAction,
-    pub resume_command: Option<String>,
+    /// Replace `command` in X11 / Wayland sessions (by `XDG_SESSION_TYPE`)
+    pub command_x11: Option<String>,
+    pub command_wayland: Option<String>,
+    /// `resume_command`: one command or a list, run in order when the user returns
+    pub resume_commands: Vec<String>,
+    /// Run `resume_command` when the user returns from idle, not only after a system resume
+    pub also_run_on_activity: bool,
+    /// Fire at most once per system boot (tracked by boot id in the runtime dir)
+    pub once_per_boot: bool,
+    /// Count `timeout` from the previous action (true, default) or from the last activity
+    pub relative: bool,
+    /// Name of an action in the same block that must have fired (and, for a lock,
+    /// still be locked) before this one may fire
+    pub requires: Option<String>,
+    /// Check that a lock_screen / dpms action took effect and log loudly if not
+    pub verify: bool,
+    /// Extra environment for this action's commands (`env:` block), overriding
+    /// inherited variables of the same name
+    pub env: Vec<(String, String)>,
+    /// Never fire on a timeout, only through `trigger` (e.g. a shutdown keybind)
+    pub manual_only: bool,
+    /// Only fire within this local time-of-day window (`active_from` / `active_until`)
+    pub window: Option<TimeWindow>,
+    /// Directory the action's commands run in (`working_directory`, `~` and
+    /// `$VAR` expanded); None keeps the daemon's
+    pub working_directory: Option<PathBuf>,
     pub last_triggered: Option<Instant>,
 }
 
@@ -36,8 +78,93 @@ impl IdleActionBlock {
     pub fn is_instant(&self) -> bool {
         self.timeout == 0
     }
+    /// The command for the current session type, falling back to `command`
+    pub fn session_command(&self) -> &str {
+        let specific = match session_type().as_deref() {
+            Some("x11") => self.command_x11.as_deref(),
+            Some("wayland") => self.command_wayland.as_deref(),
+            _ => None,
+        };
+        specific.unwrap_or(&self.command)
+    }
+    /// How to spawn this action's commands under the config's `action_shell`
+    pub fn command_options(&self, shell: ActionShell) -> CommandOptions {
+        CommandOptions {
+            shell,
+            env: self.env.clone(),
+            working_directory: self.working_directory.clone(),
+            ..Default::default()
+        }
+    }
     pub fn has_resume_command(&self) -> bool {
-        self.resume_command.is_some()
+        !self.resume_commands.is_empty()
+    }
+    /// When this action is due. Relative actions count from the previous action in
+    /// the sequence (its fire time is stored in `last_triggered` when the sequence
+    /// advances), absolute ones from the last user activity.
+    pub fn fire_time(&self, last_activity: Instant) -> Instant {
+        let anchor = if self.relative {
+            self.last_triggered.unwrap_or(last_activity)
+        } else {
+            last_activity
+        };
+        anchor + Duration::from_secs(self.timeout)
+    }
+    /// Action name without its "ac." / "battery." / "profile.<name>." block prefix
+    pub fn base_name(&self) -> &str {
+        if let Some((_, name)) = self.name.strip_prefix("profile.").and_then(|rest| rest.split_once('.')) {
+            return name;
+        }
+        self.name
+            .strip_prefix("ac.")
+            .or_else(|| self.name.strip_prefix("battery."))
+            .unwrap_or(&self.name)
+    }
+}
+
+/// `battery:` block: run `command` once the battery discharges below
+/// `percent`, whatever the idle state; armed again after charging to `rearm_percent`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatteryCritical {
+    pub percent: u8,
+    pub rearm_percent: u8,
+    pub command: String,
+}
+
+/// A local time-of-day range; `from` after `until` wraps past midnight
+/// (22:00-07:00 covers the night)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub from: NaiveTime,
+    pub until: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Parse "HH:MM" (or "HH:MM:SS")
+    pub fn parse_time(s: &str) -> Option<NaiveTime> {
+        let s = s.trim();
+        NaiveTime::parse_from_str(s, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S"))
+            .ok()
+    }
+
+    /// `from` inclusive, `until` exclusive; equal bounds mean all day
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.until {
+            self.from == self.until || (self.from <= time && time < self.until)
+        } else {
+            time >= self.from || time < self.until
+        }
+    }
+
+    pub fn contains_now(&self) -> bool {
+        self.contains(chrono::Local::now().time())
+    }
+}
+
+impl Display for TimeWindow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}-{}", self.from.format("%H:%M"), self.until.format("%H:%M"))
     }
 }
 
@@ -47,6 +174,29 @@ pub enum AppInhibitPattern {
     Regex(Regex),
 }
 
+impl AppInhibitPattern {
+    /// Literals match case-insensitively as a whole word, so `bluez` matches
+    /// `org.mpris.MediaPlayer2.bluez` but not `bluezilla`; regexes match as written
+    pub fn matches_word(&self, haystack: &str) -> bool {
+        match self {
+            AppInhibitPattern::Regex(r) => r.is_match(haystack),
+            AppInhibitPattern::Literal(s) => {
+                let needle = s.to_lowercase();
+                let haystack = haystack.to_lowercase();
+                if needle.is_empty() {
+                    return false;
+                }
+                haystack.match_indices(&needle).any(|(start, _)| {
+                    let end = start + needle.len();
+                    let before = haystack[..start].chars().next_back();
+                    let after = haystack[end..].chars().next();
+                    !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+                })
+            }
+        }
+    }
+}
+
 impl Display for AppInhibitPattern {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
@@ -71,17 +221,211 @@ pub enum LidOpenAction {
     Custom(String),
 }
 
+/// How `trigger all` dispatches a block's actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ActionDispatch {
+    /// One after another, in config order
+    #[default]
+    Sequential,
+    /// Custom actions start concurrently first; lock, DPMS, brightness and
+    /// suspend then follow in config order
+    Parallel,
+}
+
+impl Display for ActionDispatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ActionDispatch::Sequential => write!(f, "sequential"),
+            ActionDispatch::Parallel => write!(f, "parallel"),
+        }
+    }
+}
+
+/// How action and hook commands are started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionShell {
+    /// `<shell> -c <command>`
+    Shell(String),
+    /// Split the command into argv (honoring quotes) and exec it directly:
+    /// no expansion, pipes or redirections
+    None,
+}
+
+impl Default for ActionShell {
+    fn default() -> Self {
+        ActionShell::Shell("sh".to_string())
+    }
+}
+
+impl Display for ActionShell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ActionShell::Shell(shell) => write!(f, "{}", shell),
+            ActionShell::None => write!(f, "none"),
+        }
+    }
+}
+
+/// How brightness is captured and restored around brightness actions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BrightnessBackend {
+    /// sysfs if a backlight is readable, else brightnessctl; brightness actions
+    /// are disabled when neither works
+    #[default]
+    Auto,
+    Sysfs,
+    Brightnessctl,
+    /// Never capture or restore; brightness actions still run their commands
+    None,
+}
+
+impl Display for BrightnessBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            BrightnessBackend::Auto => write!(f, "auto"),
+            BrightnessBackend::Sysfs => write!(f, "sysfs"),
+            BrightnessBackend::Brightnessctl => write!(f, "brightnessctl"),
+            BrightnessBackend::None => write!(f, "none"),
+        }
+    }
+}
+
+/// Commands run exactly when the power source changes (not idle-based)
+#[derive(Debug, Clone, Default)]
+pub struct PowerChangeCommands {
+    pub to_ac_command: Option<String>,
+    pub to_battery_command: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct StasisConfig {
     pub actions: Vec<IdleActionBlock>,
-    pub debounce_seconds: u8,
+    /// Ignore activity this long after coming back from idle (`debounce_seconds`,
+    /// whole or fractional seconds or a duration string such as "500ms")
+    pub debounce: Duration,
+    /// Matched against both window app_ids and titles (process names when
+    /// the compositor can't be queried)
     pub inhibit_apps: Vec<AppInhibitPattern>,
+    /// Matched against window app_ids only
+    pub inhibit_app_ids: Vec<AppInhibitPattern>,
+    /// Matched against window titles only
+    pub inhibit_titles: Vec<AppInhibitPattern>,
+    /// Pause idle timers while the focused window is fullscreen (Hyprland only)
+    pub inhibit_fullscreen: bool,
     pub monitor_media: bool,
     pub ignore_remote_media: bool,
     pub pre_suspend_command: Option<String>,
+    /// Wait after starting `pre_suspend_command` before suspending; 0 disables
+    pub pre_suspend_settle_ms: u64,
     pub respect_wayland_inhibitors: bool,
     pub lid_close_action: LidCloseAction,
-    pub lid_open_action: LidOpenAction
+    pub lid_open_action: LidOpenAction,
+    pub log_level: Option<LogLevel>,
+    pub metrics_listen: Option<String>,
+    pub trigger_instant_on_startup: bool,
+    /// libinput device names (case-insensitive substrings) whose events never reset idle
+    pub ignore_input_devices: Vec<String>,
+    /// Pointer travel (libinput units, ~pixels) needed before motion counts as activity; 0 disables
+    pub min_activity_movement: f64,
+    pub on_lock_command: Option<String>,
+    pub on_unlock_command: Option<String>,
+    pub on_power_change: PowerChangeCommands,
+    pub action_dispatch: ActionDispatch,
+    /// After an unlock, hold the lock action for this long (0 disables).
+    /// Unlike `debounce` this only affects lock actions
+    pub relock_grace_seconds: u64,
+    /// Extra players (identity or bus name) treated as remote by `ignore_remote_media`
+    pub ignored_players: Vec<AppInhibitPattern>,
+    /// Suspend once the session has stayed locked this long; None disables
+    pub lock_then_suspend_after: Option<Duration>,
+    /// Capture brightness before brightness actions and restore it on activity.
+    /// Turn off when an external daemon (clight, wluma...) owns brightness
+    pub manage_brightness: bool,
+    /// Treat media stopping as activity, restarting the countdown instead of resuming it
+    pub reset_idle_on_media_stop: bool,
+    /// Count network throughput above `network_activity_threshold_kbps` as activity
+    /// (headless machines without input devices)
+    pub network_activity_inhibit: bool,
+    /// Combined receive + transmit rate (kilobits/s, loopback excluded) that counts as activity
+    pub network_activity_threshold_kbps: u64,
+    /// Inhibit idle while the 1-minute load average is above this; None disables
+    pub inhibit_above_load: Option<f64>,
+    /// Process names (exact) that inhibit idle while running
+    pub inhibit_processes: Vec<String>,
+    /// Seconds between `inhibit_processes` scans
+    pub inhibit_process_interval: u64,
+    /// Shell used to run commands, or `none` to exec them directly
+    pub action_shell: ActionShell,
+    /// Coalesce activity events this close to the last reset (0 disables)
+    pub activity_debounce_ms: u64,
+    /// Run before locking; success means the session is already locked and the lock is skipped
+    pub lock_check_command: Option<String>,
+    /// Named action sets (`profile.<name>`) selected with `set-profile`; while one
+    /// is active it replaces the power-based ac/battery/default choice
+    pub profiles: BTreeMap<String, Vec<IdleActionBlock>>,
+    /// `manual_only` actions of every block, kept out of the timed sequences
+    pub manual_actions: Vec<IdleActionBlock>,
+    /// Append a JSON line per action, pause/resume, power change and lock/unlock here
+    pub event_log: Option<PathBuf>,
+    /// false: skip suspend actions while a remote (e.g. SSH) logind session is open
+    pub suspend_if_sessions: bool,
+    /// Automatic inhibition (media, apps, inhibitors...) lasting longer than this is
+    /// overridden and the actions run anyway; manual pauses are not affected
+    pub max_inhibit_seconds: Option<u64>,
+    /// Action run when the battery runs critically low, independent of idle
+    pub battery_critical: Option<BatteryCritical>,
+    /// Lock the session (and wait for the locker) before any suspend: idle, lid,
+    /// manual trigger or panic
+    pub lock_before_suspend: bool,
+    /// How brightness is captured and restored (`auto`, `sysfs`, `brightnessctl`, `none`)
+    pub brightness_backend: BrightnessBackend,
+    /// Warn this long (with a desktop notification) before an idle suspend; activity
+    /// during the countdown cancels it. 0 disables
+    pub suspend_countdown_seconds: u64,
+    /// Undo dimming and DPMS when the idle action pipeline fails unexpectedly
+    pub restore_on_error: bool,
+    /// Run queued resume commands one after another (each waiting for the last)
+    /// instead of launching them all at once
+    pub resume_sequential: bool,
+    /// Switch between the AC and battery blocks only once the new power source has
+    /// held this long (0 switches at once)
+    pub power_debounce_seconds: u64,
+    /// Pass over brightness and DPMS actions while the session is locked, for
+    /// lockers that blank the screen themselves
+    pub skip_display_while_locked: bool,
+}
+
+impl StasisConfig {
+    /// Which actions would have fired after `idle_seconds` without activity, and
+    /// at which second, following the same timeout and ordering rules as the
+    /// idle loop. Pure: no commands run and no state is touched, so it can check
+    /// a schedule in CI. `on_battery` picks the block on laptop configs.
+    pub fn simulate(&self, idle_seconds: u64, on_battery: bool) -> Vec<(&IdleActionBlock, u64)> {
+        let prefix = if on_battery { "battery." } else { "ac." };
+        let has_power_blocks = self.actions.iter().any(|a| a.name.starts_with("ac.") || a.name.starts_with("battery."));
+        let block = self.actions.iter().filter(|a| {
+            if has_power_blocks {
+                a.name.starts_with(prefix)
+            } else {
+                true
+            }
+        });
+
+        let mut fired = Vec::new();
+        let mut previous = 0u64;
+        for action in block {
+            let anchor = if action.relative { previous } else { 0 };
+            // The sequence advances one action at a time, so an absolute timeout
+            // shorter than the previous action's still waits for it
+            let at = (anchor + action.timeout).max(previous);
+            if at > idle_seconds {
+                break;
+            }
+            fired.push((action, at));
+            previous = at;
+        }
+        fired
+    }
 }
 
 impl std::fmt::Display for LidCloseAction {
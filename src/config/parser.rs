@@ -1,40 +1,289 @@
-use eyre::{Result, eyre, WrapErr};
 use regex::Regex;
 use rune_cfg::{RuneConfig, Value};
-use crate::config::model::*;
-use crate::log::log_message;
-use crate::core::utils::is_laptop;
+use crate::config::{error::ConfigError, keys::is_special_key, model::*};
+use crate::log::{log_error_message, log_message, LogLevel};
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+use crate::core::utils::{is_laptop, parse_duration};
 
 // --- helpers ---
-fn parse_app_pattern(s: &str) -> Result<AppInhibitPattern> {
+fn parse_app_pattern(s: &str) -> Result<AppInhibitPattern, ConfigError> {
     let regex_meta = ['.', '*', '+', '?', '(', ')', '[', ']', '{', '}', '|', '\\', '^', '$'];
     if s.chars().any(|c| regex_meta.contains(&c)) {
-        Ok(AppInhibitPattern::Regex(Regex::new(s).wrap_err("invalid regex in inhibit_apps")?))
+        Regex::new(s)
+            .map(AppInhibitPattern::Regex)
+            .map_err(|e| ConfigError::InvalidValue {
+                key: "stasis.inhibit_apps".to_string(),
+                message: e.to_string(),
+            })
     } else {
         Ok(AppInhibitPattern::Literal(s.to_string()))
     }
 }
 
-fn is_special_key(key: &str) -> bool {
-    matches!(
-        key,
-        "resume_command" | "resume-command"
-            | "pre_suspend_command" | "pre-suspend-command"
-            | "monitor_media" | "monitor-media"
-            | "ignore_remote_media" | "ignore-remote-media"
-            | "respect_wayland_inhibitors" | "respect-wayland-inhibitors"
-            | "inhibit_apps" | "inhibit-apps"
-            | "debounce_seconds" | "debounce-seconds"
-    )
+/// Expand a leading `~/` and `$VAR` / `${VAR}` references in a path; an unset
+/// variable is an error rather than silently becoming empty
+fn expand_path(raw: &str, key: &str) -> Result<PathBuf, ConfigError> {
+    let unset = |name: &str| ConfigError::InvalidValue {
+        key: key.to_string(),
+        message: format!("environment variable '{}' is not set", name),
+    };
+
+    let mut out = String::new();
+    let mut rest = raw;
+    if let Some(tail) = raw.strip_prefix("~/").or((raw == "~").then_some("")) {
+        let home = dirs::home_dir().ok_or_else(|| unset("HOME"))?;
+        out.push_str(&home.to_string_lossy());
+        out.push('/');
+        rest = tail;
+    }
+
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, tail) = match after.strip_prefix('{').and_then(|a| a.split_once('}')) {
+            Some((name, tail)) => (name, tail),
+            None => {
+                let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        if name.is_empty() {
+            out.push('$');
+        } else {
+            out.push_str(&std::env::var(name).map_err(|_| unset(name))?);
+        }
+        rest = tail;
+    }
+    out.push_str(rest);
+    Ok(PathBuf::from(out))
+}
+
+/// Reads a list of app patterns; plain strings containing regex metacharacters
+/// and `r"..."` entries become regexes, anything unparsable is dropped
+fn parse_app_pattern_list(config: &RuneConfig, snake: &str) -> Vec<AppInhibitPattern> {
+    config
+        .get_value(&format!("stasis.{}", snake))
+        .or_else(|_| config.get_value(&format!("stasis.{}", snake.replace('_', "-"))))
+        .ok()
+        .and_then(|v| match v {
+            Value::Array(arr) => Some(
+                arr.iter()
+                    .filter_map(|v| match v {
+                        Value::String(s) => parse_app_pattern(s).ok(),
+                        Value::Regex(s) => Regex::new(s).ok().map(AppInhibitPattern::Regex),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Keys an action block understands (snake_case; kebab-case is accepted too)
+const ACTION_KEYS: &[&str] = &[
+    "command", "command_x11", "command_wayland", "kind", "timeout", "after",
+    "resume_command", "also_run_on_activity", "once_per_boot", "relative",
+    "requires", "verify", "env", "manual_only", "active_from", "active_until",
+    "working_directory",
+];
+
+/// Settings blocks under `stasis` and the keys inside them
+const SETTING_BLOCKS: &[(&str, &[&str])] = &[
+    ("battery", &["critical_percent", "critical_action", "rearm_percent"]),
+    ("on_power_change", &["to_ac_command", "to_battery_command"]),
+];
+
+/// Warn about keys nothing reads, which are otherwise silently ignored: a typo
+/// such as `timout` or `pre_suspemd_command` just looks like a default
+fn warn_unknown_keys(config: &RuneConfig, file: &str) {
+    let mut unknown = Vec::new();
+    for key in config.get_keys("stasis").unwrap_or_default() {
+        let path = format!("stasis.{}", key);
+        match key.replace('-', "_").as_str() {
+            "on_ac" | "on_battery" | "chain" => unknown_action_keys(config, &path, &mut unknown),
+            "profile" => {
+                for name in config.get_keys(&path).unwrap_or_default() {
+                    unknown_action_keys(config, &format!("{}.{}", path, name), &mut unknown);
+                }
+            }
+            // Template names are the user's own
+            "templates" => {}
+            snake => match SETTING_BLOCKS.iter().find(|(block, _)| *block == snake) {
+                Some((_, known)) => unknown.extend(
+                    config.get_keys(&path).unwrap_or_default().into_iter()
+                        .filter(|k| !known.contains(&k.replace('-', "_").as_str()))
+                        .map(|k| format!("{}.{}", path, k)),
+                ),
+                None if is_special_key(&key) => {}
+                // Anything else is an action on desktops
+                None => match config.get_keys(&path) {
+                    Ok(fields) => unknown_action_fields(&path, fields, &mut unknown),
+                    Err(_) => unknown.push(path),
+                },
+            },
+        }
+    }
+
+    for key in unknown {
+        log_error_message(&format!("Warning: {}: unknown config key '{}', ignoring it", file, key));
+    }
+}
+
+/// Unknown keys of a block of actions (`on_ac:`, a profile, a chain)
+fn unknown_action_keys(config: &RuneConfig, path: &str, unknown: &mut Vec<String>) {
+    for key in config.get_keys(path).unwrap_or_default() {
+        let key_path = format!("{}.{}", path, key);
+        if key == "chain" {
+            unknown_action_keys(config, &key_path, unknown);
+        } else if is_special_key(&key) {
+            continue;
+        } else {
+            match config.get_keys(&key_path) {
+                Ok(fields) => unknown_action_fields(&key_path, fields, unknown),
+                Err(_) => unknown.push(key_path),
+            }
+        }
+    }
+}
+
+fn unknown_action_fields(path: &str, fields: Vec<String>, unknown: &mut Vec<String>) {
+    unknown.extend(
+        fields.into_iter()
+            .filter(|f| !ACTION_KEYS.contains(&f.replace('-', "_").as_str()))
+            .map(|f| format!("{}.{}", path, f)),
+    );
+}
+
+/// Read an action's `timeout` in seconds. `timeout = 0` marks an instant action,
+/// fired at startup (or on the first idle cycle) instead of after a delay.
+/// Returns `Ok(None)` when the key is missing, an error for negative, fractional,
+/// out-of-range or non-numeric values.
+fn parse_timeout(config: &RuneConfig, timeout_path: &str) -> Result<Option<u64>, ConfigError> {
+    let value = match config.get_value(timeout_path)
+        .or_else(|_| config.get_value(&timeout_path.replace('-', "_")))
+    {
+        Ok(v) => v,
+        Err(_) => return Ok(None),
+    };
+
+    let invalid = |message: String| ConfigError::InvalidValue {
+        key: timeout_path.to_string(),
+        message,
+    };
+
+    match value {
+        Value::Number(n) if !n.is_finite() || n >= u64::MAX as f64 => {
+            Err(invalid(format!("{} is out of range", n)))
+        }
+        Value::Number(n) if n < 0.0 => {
+            Err(invalid(format!("{} is negative; use 0 for an instant action", n)))
+        }
+        Value::Number(n) if n.fract() != 0.0 => {
+            Err(invalid(format!("{} is not a whole number of seconds", n)))
+        }
+        Value::Number(n) => Ok(Some(n as u64)),
+        other => Err(invalid(format!("expected a number of seconds, got {:?}", other))),
+    }
+}
+
+/// Named command strings from the `templates:` block, referenced as `"@name"`
+fn parse_templates(config: &RuneConfig) -> Result<BTreeMap<String, String>, ConfigError> {
+    config.get_keys("stasis.templates")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|name| {
+            let key = format!("stasis.templates.{}", name);
+            config.get::<String>(&key)
+                .map(|command| (name, command))
+                .map_err(|e| ConfigError::InvalidValue { key, message: e.to_string() })
+        })
+        .collect()
+}
+
+/// Replace a whole-value `@name` reference with its template. Anything not
+/// starting with `@` is returned unchanged; an unknown name is an error.
+fn resolve_template(value: String, templates: &BTreeMap<String, String>, key: &str) -> Result<String, ConfigError> {
+    let Some(name) = value.trim().strip_prefix('@') else {
+        return Ok(value);
+    };
+    templates.get(name).cloned().ok_or_else(|| ConfigError::InvalidValue {
+        key: key.to_string(),
+        message: format!("unknown template '@{}'", name),
+    })
 }
 
-fn collect_actions(config: &RuneConfig, path: &str) -> Result<Vec<IdleActionBlock>> {
+/// Collect the action blocks under `path`, in config order. `prefix` ("ac.",
+/// "battery." or "") is prepended to each action name so the manager can split
+/// them by power block. A `chain:` block may replace the flat actions; mixing
+/// both is an error. `order_actions` puts the result in firing order.
+fn collect_actions(
+    config: &RuneConfig,
+    path: &str,
+    prefix: &str,
+    templates: &BTreeMap<String, String>,
+) -> Result<Vec<IdleActionBlock>, ConfigError> {
+    let actions = collect_block(config, path, prefix, templates, false)?;
+
+    let chain_path = format!("{}.chain", path);
+    if config.get_keys(&chain_path).is_err() {
+        return Ok(actions);
+    }
+    if !actions.is_empty() {
+        return Err(ConfigError::InvalidValue {
+            key: chain_path,
+            message: "a chain cannot be combined with flat actions in the same block".to_string(),
+        });
+    }
+    collect_block(config, &chain_path, prefix, templates, true)
+}
+
+/// Put a block's actions (after any drop-ins were merged in) in firing order:
+/// `actions_order` first, then prerequisites before their dependents. A chain
+/// keeps its listed order.
+fn order_actions(config: &RuneConfig, path: &str, actions: Vec<IdleActionBlock>) -> Result<Vec<IdleActionBlock>, ConfigError> {
+    // Accept the kebab-case spelling of the section itself (`on-ac`)
+    let kebab = path.replace('_', "-");
+    let path = if config.get_keys(path).is_ok() { path } else { kebab.as_str() };
+
+    if config.get_keys(&format!("{}.chain", path)).is_ok() {
+        return Ok(actions);
+    }
+    let actions = apply_actions_order(config, path, actions)?;
+    order_by_requirements(actions, path)
+}
+
+/// Step delay of a chain action: seconds or a duration string such as "5m"
+fn parse_after(config: &RuneConfig, after_path: &str) -> Result<Option<u64>, ConfigError> {
+    match config.get_value(after_path) {
+        Ok(Value::String(s)) => parse_duration(&s).map(|d| Some(d.as_secs())).ok_or_else(|| {
+            ConfigError::InvalidValue {
+                key: after_path.to_string(),
+                message: format!("'{}' is not a duration", s),
+            }
+        }),
+        _ => parse_timeout(config, after_path),
+    }
+}
+
+/// The actions of one block. In a chain each action's `after` counts from the
+/// previous step; it becomes an absolute timeout and the listed order is kept.
+fn collect_block(
+    config: &RuneConfig,
+    path: &str,
+    prefix: &str,
+    templates: &BTreeMap<String, String>,
+    chain: bool,
+) -> Result<Vec<IdleActionBlock>, ConfigError> {
     let mut actions = Vec::new();
+    let mut chain_elapsed = 0u64;
 
-    let keys = config
-        .get_keys(path)
-        .or_else(|_| config.get_keys(&path.replace('-', "_")))
-        .unwrap_or_default();
+    // Accept the kebab-case spelling of the section itself (`on-ac`)
+    let kebab = path.replace('_', "-");
+    let (path, keys) = match config.get_keys(path) {
+        Ok(keys) => (path, keys),
+        Err(_) => (kebab.as_str(), config.get_keys(&kebab).unwrap_or_default()),
+    };
 
     for key in keys {
         if is_special_key(&key) {
@@ -42,38 +291,167 @@ fn collect_actions(config: &RuneConfig, path: &str) -> Result<Vec<IdleActionBloc
         }
 
         let command_path = format!("{}.{}.command", path, key);
-        let command = match config.get::<String>(&command_path)
+        let command = config.get::<String>(&command_path)
             .or_else(|_| config.get::<String>(&command_path.replace('-', "_")))
-        {
-            Ok(c) => c,
-            Err(_) => continue,
+            .ok();
+        let command_x11 = config.get::<String>(&format!("{}.{}.command_x11", path, key))
+            .or_else(|_| config.get::<String>(&format!("{}.{}.command-x11", path, key)))
+            .ok();
+        let command_wayland = config.get::<String>(&format!("{}.{}.command_wayland", path, key))
+            .or_else(|_| config.get::<String>(&format!("{}.{}.command-wayland", path, key)))
+            .ok();
+
+        let resolve = |value: Option<String>, field: &str| {
+            value.map(|v| resolve_template(v, templates, &format!("{}.{}.{}", path, key, field))).transpose()
         };
+        let command = resolve(command, "command")?;
+        let command_x11 = resolve(command_x11, "command_x11")?;
+        let command_wayland = resolve(command_wayland, "command_wayland")?;
 
-        let timeout_path = format!("{}.{}.timeout", path, key);
-        let timeout = match config.get::<u64>(&timeout_path)
-            .or_else(|_| config.get::<u64>(&timeout_path.replace('-', "_")))
-        {
-            Ok(t) => t,
-            Err(_) => continue,
+        // Session-specific commands alone are enough; the other session then does nothing
+        let command = match command {
+            Some(c) => c,
+            None if command_x11.is_some() || command_wayland.is_some() => String::new(),
+            None => continue,
         };
 
-        let kind = match key.as_str() {
-            "lock_screen" | "lock-screen" => IdleAction::LockScreen,
-            "suspend" => IdleAction::Suspend,
-            "dpms" => IdleAction::Dpms,
-            "brightness" => IdleAction::Brightness,
-            _ => IdleAction::Custom,
+        let manual_only = config.get::<bool>(&format!("{}.{}.manual_only", path, key))
+            .or_else(|_| config.get::<bool>(&format!("{}.{}.manual-only", path, key)))
+            .unwrap_or(false);
+
+        // A manual-only action never runs on a timeout, so it may leave it out
+        let timeout = if chain {
+            let after_path = format!("{}.{}.after", path, key);
+            match parse_after(config, &after_path)? {
+                Some(after) if !manual_only => {
+                    chain_elapsed = chain_elapsed.saturating_add(after);
+                    chain_elapsed
+                }
+                _ if manual_only => 0,
+                _ => {
+                    return Err(ConfigError::InvalidValue {
+                        key: after_path,
+                        message: "chain actions need an `after` delay".to_string(),
+                    });
+                }
+            }
+        } else {
+            let timeout_path = format!("{}.{}.timeout", path, key);
+            match parse_timeout(config, &timeout_path)? {
+                Some(t) => t,
+                None if manual_only => 0,
+                None => continue,
+            }
+        };
+
+        // An explicit `kind` overrides the kind inferred from the block name
+        let kind_override = config.get::<String>(&format!("{}.{}.kind", path, key)).ok();
+        let kind_name = kind_override.as_deref().unwrap_or(key.as_str());
+        let kind = IdleAction::parse(kind_name).unwrap_or_else(|| {
+            if kind_override.is_some() {
+                log_message(&format!(
+                    "Unknown kind '{}' for action '{}', treating it as custom",
+                    kind_name, key
+                ));
+            }
+            IdleAction::Custom
+        });
+
+        if timeout == 0 && !manual_only && matches!(kind, IdleAction::Suspend | IdleAction::LockScreen) {
+            log_error_message(&format!(
+                "Warning: action '{}' has timeout 0, so it fires with the instant actions \
+                 (at startup, or on the first idle cycle with trigger_instant_on_startup false) \
+                 and again after every activity; this is almost always a mistake",
+                key
+            ));
+        }
+
+        // A single command or a list, run in the order given
+        let resume_commands = match config.get::<String>(&format!("{}.{}.resume_command", path, key))
+            .or_else(|_| config.get::<String>(&format!("{}.{}.resume-command", path, key)))
+        {
+            Ok(cmd) => vec![cmd],
+            Err(_) => match config.get_value(&format!("{}.{}.resume_command", path, key))
+                .or_else(|_| config.get_value(&format!("{}.{}.resume-command", path, key)))
+            {
+                Ok(Value::Array(arr)) => arr.iter()
+                    .filter_map(|v| match v {
+                        Value::String(s) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            },
         };
+        let resume_commands = resume_commands.into_iter()
+            .map(|cmd| resolve_template(cmd, templates, &format!("{}.{}.resume_command", path, key)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let also_run_on_activity = config.get::<bool>(&format!("{}.{}.also_run_on_activity", path, key))
+            .or_else(|_| config.get::<bool>(&format!("{}.{}.also-run-on-activity", path, key)))
+            .unwrap_or(false);
+
+        let once_per_boot = config.get::<bool>(&format!("{}.{}.once_per_boot", path, key))
+            .or_else(|_| config.get::<bool>(&format!("{}.{}.once-per-boot", path, key)))
+            .unwrap_or(false);
 
-        let resume_command = config.get::<String>(&format!("{}.{}.resume_command", path, key)).ok()
-            .or_else(|| config.get::<String>(&format!("{}.{}.resume-command", path, key)).ok());
+        let relative = !chain && config.get::<bool>(&format!("{}.{}.relative", path, key))
+            .unwrap_or(true);
+
+        let requires = config.get::<String>(&format!("{}.{}.requires", path, key)).ok();
+
+        let verify = config.get::<bool>(&format!("{}.{}.verify", path, key))
+            .unwrap_or(false);
+        if verify && !matches!(kind, IdleAction::LockScreen | IdleAction::Dpms) {
+            log_error_message(&format!(
+                "Warning: action '{}' sets verify, which only applies to lock_screen and dpms actions",
+                key
+            ));
+        }
+
+        let env_path = format!("{}.{}.env", path, key);
+        let env = config.get_keys(&env_path)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|var| match config.get::<String>(&format!("{}.{}", env_path, var)) {
+                Ok(value) => Ok((var, value)),
+                Err(e) => Err(ConfigError::InvalidValue {
+                    key: format!("{}.{}", env_path, var),
+                    message: e.to_string(),
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let window = parse_time_window(config, &format!("{}.{}", path, key))?;
+
+        // Existence is checked when a command is spawned: the directory may be
+        // mounted or created after the config loads
+        let working_directory = match config.get::<String>(&format!("{}.{}.working_directory", path, key))
+            .or_else(|_| config.get::<String>(&format!("{}.{}.working-directory", path, key)))
+        {
+            Ok(dir) if !dir.trim().is_empty() => {
+                Some(expand_path(dir.trim(), &format!("{}.{}.working_directory", path, key))?)
+            }
+            _ => None,
+        };
 
         actions.push(IdleActionBlock {
-            name: key.clone(),
+            name: format!("{}{}", prefix, key),
             timeout,
             command,
             kind,
-            resume_command,
+            command_x11,
+            command_wayland,
+            resume_commands,
+            also_run_on_activity,
+            once_per_boot,
+            relative,
+            requires,
+            verify,
+            env,
+            manual_only,
+            window,
+            working_directory,
             last_triggered: None,
         });
     }
@@ -81,52 +459,329 @@ fn collect_actions(config: &RuneConfig, path: &str) -> Result<Vec<IdleActionBloc
     Ok(actions)
 }
 
+/// The `battery:` block. `critical_percent` needs a `critical_action` (a
+/// command or `@template`); `rearm_percent` defaults to 5 points above it.
+fn parse_battery_critical(
+    config: &RuneConfig,
+    templates: &BTreeMap<String, String>,
+) -> Result<Option<BatteryCritical>, ConfigError> {
+    let read = |name: &str| -> Result<Option<u8>, ConfigError> {
+        let key = format!("stasis.battery.{}", name);
+        let value = config.get::<u64>(&key).or_else(|_| config.get::<u64>(&key.replace('_', "-")));
+        match default_if_missing(value.map(Some), &key, None)? {
+            Some(p) if p > 100 => Err(ConfigError::InvalidValue {
+                key,
+                message: format!("{} is not a percentage", p),
+            }),
+            p => Ok(p.map(|p| p as u8)),
+        }
+    };
+
+    let Some(percent) = read("critical_percent")? else {
+        return Ok(None);
+    };
+    let key = "stasis.battery.critical_action";
+    let command = config.get::<String>(key)
+        .or_else(|_| config.get::<String>("stasis.battery.critical-action"))
+        .map_err(|_| ConfigError::InvalidValue {
+            key: key.to_string(),
+            message: "critical_percent is set but critical_action is missing".to_string(),
+        })?;
+    let command = resolve_template(command, templates, key)?;
+    let rearm_percent = read("rearm_percent")?.unwrap_or(percent.saturating_add(5)).clamp(percent, 100);
+
+    Ok(Some(BatteryCritical { percent, rearm_percent, command }))
+}
+
+/// An action's `active_from` / `active_until` pair. Both must be given; a window
+/// whose start is later than its end wraps past midnight.
+fn parse_time_window(config: &RuneConfig, action_path: &str) -> Result<Option<TimeWindow>, ConfigError> {
+    let read = |name: &str| -> Result<Option<chrono::NaiveTime>, ConfigError> {
+        let key = format!("{}.{}", action_path, name);
+        let Some(raw) = config.get::<String>(&key)
+            .or_else(|_| config.get::<String>(&key.replace('_', "-")))
+            .ok()
+        else {
+            return Ok(None);
+        };
+        TimeWindow::parse_time(&raw).map(Some).ok_or_else(|| ConfigError::InvalidValue {
+            key,
+            message: format!("'{}' is not a time of day (expected HH:MM)", raw),
+        })
+    };
+
+    match (read("active_from")?, read("active_until")?) {
+        (Some(from), Some(until)) => Ok(Some(TimeWindow { from, until })),
+        (None, None) => Ok(None),
+        _ => Err(ConfigError::InvalidValue {
+            key: action_path.to_string(),
+            message: "active_from and active_until must be set together".to_string(),
+        }),
+    }
+}
+
+/// Reorder a block to match its `actions_order` list, if it has one. Actions
+/// not in the list follow the listed ones, sorted by timeout.
+fn apply_actions_order(
+    config: &RuneConfig,
+    path: &str,
+    mut actions: Vec<IdleActionBlock>,
+) -> Result<Vec<IdleActionBlock>, ConfigError> {
+    let key = format!("{}.actions_order", path);
+    let value = match config
+        .get_value(&key)
+        .or_else(|_| config.get_value(&format!("{}.actions-order", path)))
+    {
+        Ok(value) => value,
+        Err(_) => return Ok(actions),
+    };
+
+    let names: Vec<String> = match value {
+        Value::Array(arr) => arr
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => Ok(s.clone()),
+                other => Err(ConfigError::InvalidValue {
+                    key: key.clone(),
+                    message: format!("expected action names, got {:?}", other),
+                }),
+            })
+            .collect::<Result<_, _>>()?,
+        other => {
+            return Err(ConfigError::InvalidValue {
+                key,
+                message: format!("expected a list of action names, got {:?}", other),
+            });
+        }
+    };
+
+    let mut ordered = Vec::with_capacity(actions.len());
+    for name in &names {
+        let Some(i) = actions.iter().position(|a| a.base_name() == name) else {
+            return Err(ConfigError::InvalidValue {
+                key,
+                message: format!("no action named '{}' in this block", name),
+            });
+        };
+        ordered.push(actions.remove(i));
+    }
+
+    actions.sort_by_key(|a| a.timeout);
+    ordered.extend(actions);
+    Ok(ordered)
+}
+
+/// Check `requires` references within one block and order the block so every
+/// prerequisite comes before the actions depending on it. A dependent listed
+/// first would otherwise hold the sequence before its prerequisite could fire.
+fn order_by_requirements(actions: Vec<IdleActionBlock>, path: &str) -> Result<Vec<IdleActionBlock>, ConfigError> {
+    let position = |name: &str| actions.iter().position(|a| a.base_name() == name);
+
+    for action in &actions {
+        let Some(required) = &action.requires else { continue };
+        if position(required).is_none() {
+            return Err(ConfigError::InvalidValue {
+                key: format!("{}.{}.requires", path, action.base_name()),
+                message: format!("no action named '{}' in this block", required),
+            });
+        }
+
+        // Each action requires at most one other, so a cycle is a chain that
+        // comes back to where it started
+        let mut chain = vec![action.base_name()];
+        let mut next = action.requires.as_deref();
+        while let Some(name) = next {
+            if chain.contains(&name) {
+                chain.push(name);
+                return Err(ConfigError::InvalidValue {
+                    key: format!("{}.{}.requires", path, action.base_name()),
+                    message: format!("dependency cycle: {}", chain.join(" -> ")),
+                });
+            }
+            chain.push(name);
+            next = position(name).and_then(|i| actions[i].requires.as_deref());
+        }
+    }
+
+    // Emit each action after its prerequisite chain, otherwise in config order
+    let mut ordered: Vec<usize> = Vec::with_capacity(actions.len());
+    for start in 0..actions.len() {
+        let mut pending = Vec::new();
+        let mut current = Some(start);
+        while let Some(i) = current.filter(|i| !ordered.contains(i) && !pending.contains(i)) {
+            pending.push(i);
+            current = actions[i].requires.as_deref().and_then(position);
+        }
+        ordered.extend(pending.into_iter().rev());
+    }
+
+    if ordered.iter().enumerate().any(|(pos, &i)| pos != i) {
+        log_message(&format!(
+            "Reordered actions in '{}' so prerequisites fire first",
+            path
+        ));
+    }
+
+    let mut slots: Vec<Option<IdleActionBlock>> = actions.into_iter().map(Some).collect();
+    Ok(ordered.into_iter().filter_map(|i| slots[i].take()).collect())
+}
+
+/// Turn a rune-cfg lookup of an optional key into `ConfigError::InvalidValue`
+/// unless it is merely missing
+fn default_if_missing<T>(result: Result<T, impl std::fmt::Display>, key: &str, default: T) -> Result<T, ConfigError> {
+    result.or_else(|err| {
+        // only fallback if it's a "not found" error
+        if err.to_string().contains("not found") {
+            Ok(default)
+        } else {
+            Err(ConfigError::InvalidValue { key: key.to_string(), message: err.to_string() })
+        }
+    })
+}
+
 // --- main loader ---
-pub fn load_config(path: &str) -> Result<StasisConfig> {
-    let config = RuneConfig::from_file(path)
-        .wrap_err_with(|| eyre!("failed to load Rune config from '{}'", path))?;
+pub fn load_config(path: &str) -> Result<StasisConfig, ConfigError> {
+    if !std::path::Path::new(path).exists() {
+        return Err(ConfigError::NotFound(path.to_string()));
+    }
 
+    let config = RuneConfig::from_file(path).map_err(|e| ConfigError::Parse {
+        path: path.to_string(),
+        message: e.to_string(),
+    })?;
+
+    let mut drop_ins = Vec::new();
+    for file in drop_in_files() {
+        let file = file.display().to_string();
+        let drop_in = RuneConfig::from_file(&file).map_err(|e| ConfigError::Parse {
+            path: file.clone(),
+            message: e.to_string(),
+        })?;
+        log_message(&format!("Merging drop-in {}", file));
+        warn_unknown_keys(&drop_in, &file);
+        drop_ins.push(drop_in);
+    }
+    warn_unknown_keys(&config, path);
+
+    parse_config(&config, &drop_ins)
+}
+
+/// Drop-in directories, lowest precedence first: the system one, then the per-user one
+fn drop_in_dirs() -> Vec<PathBuf> {
+    let mut found = vec![PathBuf::from("/etc/stasis/config.d")];
+    found.extend(dirs::config_dir().map(|dir| dir.join("stasis/config.d")));
+    found
+}
+
+/// `*.rune` files from the drop-in directories in merge order: by directory,
+/// then by file name within each
+pub fn drop_in_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in drop_in_dirs() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut in_dir: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "rune"))
+            .collect();
+        in_dir.sort();
+        files.extend(in_dir);
+    }
+    files
+}
+
+/// Merge drop-in actions: one with the same name replaces the earlier action in
+/// place, a new one is appended (and placed by `order_actions` afterwards)
+fn merge_actions(actions: &mut Vec<IdleActionBlock>, extra: Vec<IdleActionBlock>) {
+    for action in extra {
+        match actions.iter_mut().find(|a| a.name == action.name) {
+            Some(existing) => {
+                log_message(&format!("Drop-in overrides action '{}'", action.name));
+                *existing = action;
+            }
+            None => actions.push(action),
+        }
+    }
+}
+
+/// Parse a config given as a string (stdin, tests) instead of a file
+pub fn load_config_from_str(content: &str) -> Result<StasisConfig, ConfigError> {
+    let config = RuneConfig::from_str(content).map_err(|e| ConfigError::Parse {
+        path: "<string>".to_string(),
+        message: e.to_string(),
+    })?;
+    warn_unknown_keys(&config, "<string>");
+
+    parse_config(&config, &[])
+}
+
+/// Parse the main config, then merge the actions of each drop-in in order
+fn parse_config(config: &RuneConfig, drop_ins: &[RuneConfig]) -> Result<StasisConfig, ConfigError> {
     let pre_suspend_command = config
         .get::<String>("stasis.pre_suspend_command")
         .or_else(|_| config.get::<String>("stasis.pre-suspend-command"))
         .ok();
 
-    let monitor_media = config
-        .get::<bool>("stasis.monitor_media")
-        .or_else(|_| config.get::<bool>("stasis.monitor-media"))
-        .or_else(|err| {
-            // only fallback if it's a "not found" error
-            if err.to_string().contains("not found") {
-                Ok(true)
-            } else {
-                Err(err)
-            }
-        })
-        .wrap_err("invalid value for 'stasis.monitor_media'")?;
-
-    let ignore_remote_media = config
-        .get::<bool>("stasis.ignore_remote_media")
-        .or_else(|_| config.get::<bool>("stasis.ignore-remote-media"))
-        .or_else(|err| {
-            if err.to_string().contains("not found") {
-                Ok(true)
-            } else {
-                Err(err)
-            }
-        })
-        .wrap_err("invalid value for 'stasis.ignore_remote_media'")?;
-
-    let respect_wayland_inhibitors = config
-        .get::<bool>("stasis.respect_wayland_inhibitors")
-        .or_else(|_| config.get::<bool>("stasis.respect-wayland-inhibitors"))
-        .or_else(|err| {
-            if err.to_string().contains("not found") {
-                Ok(true)
-            } else {
-                Err(err)
-            }
-        })
-        .wrap_err("invalid value for 'stasis.respect_wayland_inhibitors'")?;
+    let pre_suspend_settle_ms = config
+        .get::<u64>("stasis.pre_suspend_settle_ms")
+        .or_else(|_| config.get::<u64>("stasis.pre-suspend-settle-ms"))
+        .unwrap_or(700);
+
+    let on_lock_command = config
+        .get::<String>("stasis.on_lock_command")
+        .or_else(|_| config.get::<String>("stasis.on-lock-command"))
+        .ok();
+
+    let on_unlock_command = config
+        .get::<String>("stasis.on_unlock_command")
+        .or_else(|_| config.get::<String>("stasis.on-unlock-command"))
+        .ok();
+
+    let on_power_change = PowerChangeCommands {
+        to_ac_command: config
+            .get::<String>("stasis.on_power_change.to_ac_command")
+            .or_else(|_| config.get::<String>("stasis.on-power-change.to-ac-command"))
+            .ok(),
+        to_battery_command: config
+            .get::<String>("stasis.on_power_change.to_battery_command")
+            .or_else(|_| config.get::<String>("stasis.on-power-change.to-battery-command"))
+            .ok(),
+    };
+
+    let monitor_media = default_if_missing(
+        config
+            .get::<bool>("stasis.monitor_media")
+            .or_else(|_| config.get::<bool>("stasis.monitor-media")),
+        "stasis.monitor_media",
+        true,
+    )?;
+
+    let ignore_remote_media = default_if_missing(
+        config
+            .get::<bool>("stasis.ignore_remote_media")
+            .or_else(|_| config.get::<bool>("stasis.ignore-remote-media")),
+        "stasis.ignore_remote_media",
+        true,
+    )?;
+
+    let respect_wayland_inhibitors = default_if_missing(
+        config
+            .get::<bool>("stasis.respect_wayland_inhibitors")
+            .or_else(|_| config.get::<bool>("stasis.respect-wayland-inhibitors")),
+        "stasis.respect_wayland_inhibitors",
+        true,
+    )?;
+
+    let trigger_instant_on_startup = default_if_missing(
+        config
+            .get::<bool>("stasis.trigger_instant_on_startup")
+            .or_else(|_| config.get::<bool>("stasis.trigger-instant-on-startup")),
+        "stasis.trigger_instant_on_startup",
+        true,
+    )?;
+
+    let templates = parse_templates(config)?;
 
     let lid_close_action = config
             .get::<String>("stasis.lid_close_action")
@@ -148,6 +803,12 @@ pub fn load_config(path: &str) -> Result<StasisConfig> {
                 }
             })
             .unwrap_or(LidCloseAction::Ignore);
+    let lid_close_action = match lid_close_action {
+        LidCloseAction::Custom(cmd) => {
+            LidCloseAction::Custom(resolve_template(cmd, &templates, "stasis.lid_close_action")?)
+        }
+        other => other,
+    };
 
     let lid_open_action = config
             .get::<String>("stasis.lid_open_action")
@@ -168,15 +829,108 @@ pub fn load_config(path: &str) -> Result<StasisConfig> {
                 }
             })
             .unwrap_or(LidOpenAction::Ignore);
+    let lid_open_action = match lid_open_action {
+        LidOpenAction::Custom(cmd) => {
+            LidOpenAction::Custom(resolve_template(cmd, &templates, "stasis.lid_open_action")?)
+        }
+        other => other,
+    };
+
+    // Whole seconds, fractional seconds or a duration string ("500ms", "2s")
+    let debounce_keys = ["stasis.debounce_seconds", "stasis.debounce-seconds", "stasis.debounce"];
+    let debounce = debounce_keys
+        .iter()
+        .find_map(|key| {
+            config.get::<u64>(key).map(Duration::from_secs).ok()
+                .or_else(|| config.get::<f64>(key).ok().and_then(|secs| Duration::try_from_secs_f64(secs).ok()))
+                .or_else(|| {
+                    let s = config.get::<String>(key).ok()?;
+                    let parsed = parse_duration(&s);
+                    if parsed.is_none() {
+                        log_message(&format!("Invalid debounce '{}', using the default 3s", s));
+                    }
+                    parsed
+                })
+        })
+        .unwrap_or(Duration::from_secs(3));
+
+    let log_level = config
+        .get::<String>("stasis.log_level")
+        .or_else(|_| config.get::<String>("stasis.log-level"))
+        .ok()
+        .and_then(|s| {
+            let level = LogLevel::parse(&s);
+            if level.is_none() {
+                log_message(&format!("Unknown log_level '{}', ignoring", s));
+            }
+            level
+        });
+
+    let metrics_listen = config
+        .get::<String>("stasis.metrics_listen")
+        .or_else(|_| config.get::<String>("stasis.metrics-listen"))
+        .ok();
 
-    let debounce_seconds = config
-        .get::<u8>("stasis.debounce_seconds")
-        .or_else(|_| config.get::<u8>("stasis.debounce-seconds"))
-        .unwrap_or(3u8);
+    let inhibit_apps = parse_app_pattern_list(config, "inhibit_apps");
+    let inhibit_app_ids = parse_app_pattern_list(config, "inhibit_app_ids");
+    let inhibit_titles = parse_app_pattern_list(config, "inhibit_titles");
 
-    let inhibit_apps: Vec<AppInhibitPattern> = config
-        .get_value("stasis.inhibit_apps")
-        .or_else(|_| config.get_value("stasis.inhibit-apps"))
+    let inhibit_fullscreen = default_if_missing(
+        config
+            .get::<bool>("stasis.inhibit_fullscreen")
+            .or_else(|_| config.get::<bool>("stasis.inhibit-fullscreen")),
+        "stasis.inhibit_fullscreen",
+        false,
+    )?;
+
+    let ignore_input_devices: Vec<String> = config
+        .get_value("stasis.ignore_input_devices")
+        .or_else(|_| config.get_value("stasis.ignore-input-devices"))
+        .ok()
+        .and_then(|v| match v {
+            Value::Array(arr) => Some(
+                arr.iter()
+                    .filter_map(|v| match v {
+                        Value::String(s) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let min_activity_movement = config
+        .get::<f64>("stasis.min_activity_movement")
+        .or_else(|_| config.get::<f64>("stasis.min-activity-movement"))
+        .unwrap_or(0.0)
+        .max(0.0);
+
+    let action_dispatch = config
+        .get::<String>("stasis.action_dispatch")
+        .or_else(|_| config.get::<String>("stasis.action-dispatch"))
+        .ok()
+        .map(|s| match s.as_str() {
+            "sequential" => ActionDispatch::Sequential,
+            "parallel" => ActionDispatch::Parallel,
+            _ => {
+                log_message(&format!(
+                    "Unknown action_dispatch '{}', defaulting to sequential",
+                    s
+                ));
+                ActionDispatch::Sequential
+            }
+        })
+        .unwrap_or_default();
+
+    let relock_grace_seconds = config
+        .get::<u64>("stasis.relock_grace_seconds")
+        .or_else(|_| config.get::<u64>("stasis.relock-grace-seconds"))
+        .unwrap_or(0);
+
+    let ignored_players: Vec<AppInhibitPattern> = config
+        .get_value("stasis.ignored_players")
+        .or_else(|_| config.get_value("stasis.ignored-players"))
         .ok()
         .and_then(|v| match v {
             Value::Array(arr) => Some(
@@ -192,37 +946,261 @@ pub fn load_config(path: &str) -> Result<StasisConfig> {
         })
         .unwrap_or_default();
 
+    // Seconds, or a duration string such as "30m"
+    let lock_then_suspend_after = config
+        .get::<u64>("stasis.lock_then_suspend_after")
+        .or_else(|_| config.get::<u64>("stasis.lock-then-suspend-after"))
+        .map(Duration::from_secs)
+        .ok()
+        .or_else(|| {
+            let s = config
+                .get::<String>("stasis.lock_then_suspend_after")
+                .or_else(|_| config.get::<String>("stasis.lock-then-suspend-after"))
+                .ok()?;
+            let parsed = parse_duration(&s);
+            if parsed.is_none() {
+                log_message(&format!("Invalid lock_then_suspend_after '{}', ignoring", s));
+            }
+            parsed
+        })
+        .filter(|d| !d.is_zero());
+
+    let manage_brightness = default_if_missing(
+        config
+            .get::<bool>("stasis.manage_brightness")
+            .or_else(|_| config.get::<bool>("stasis.manage-brightness")),
+        "stasis.manage_brightness",
+        true,
+    )?;
+
+    let reset_idle_on_media_stop = config
+        .get::<bool>("stasis.reset_idle_on_media_stop")
+        .or_else(|_| config.get::<bool>("stasis.reset-idle-on-media-stop"))
+        .unwrap_or(false);
+
+    let network_activity_inhibit = config
+        .get::<bool>("stasis.network_activity_inhibit")
+        .or_else(|_| config.get::<bool>("stasis.network-activity-inhibit"))
+        .unwrap_or(false);
+
+    let network_activity_threshold_kbps = config
+        .get::<u64>("stasis.network_activity_threshold_kbps")
+        .or_else(|_| config.get::<u64>("stasis.network-activity-threshold-kbps"))
+        .unwrap_or(100);
+
+    let inhibit_above_load = match config
+        .get::<f64>("stasis.inhibit_above_load")
+        .or_else(|_| config.get::<f64>("stasis.inhibit-above-load"))
+    {
+        Ok(load) if load > 0.0 => Some(load),
+        Ok(load) => {
+            return Err(ConfigError::InvalidValue {
+                key: "stasis.inhibit_above_load".to_string(),
+                message: format!("must be a positive load average, got {}", load),
+            });
+        }
+        Err(_) => None,
+    };
+
+    let inhibit_processes: Vec<String> = config
+        .get_value("stasis.inhibit_processes")
+        .or_else(|_| config.get_value("stasis.inhibit-processes"))
+        .ok()
+        .and_then(|v| match v {
+            Value::Array(arr) => Some(
+                arr.iter()
+                    .filter_map(|v| match v {
+                        Value::String(s) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let inhibit_process_interval = config
+        .get::<u64>("stasis.inhibit_process_interval")
+        .or_else(|_| config.get::<u64>("stasis.inhibit-process-interval"))
+        .unwrap_or(5)
+        .max(1);
+
+    let action_shell = config
+        .get::<String>("stasis.action_shell")
+        .or_else(|_| config.get::<String>("stasis.action-shell"))
+        .ok()
+        .map(|s| match s.trim() {
+            "none" => ActionShell::None,
+            shell => ActionShell::Shell(shell.to_string()),
+        })
+        .unwrap_or_default();
+
+    let activity_debounce_ms = config
+        .get::<u64>("stasis.activity_debounce_ms")
+        .or_else(|_| config.get::<u64>("stasis.activity-debounce-ms"))
+        .unwrap_or(0);
+
+    let lock_check_command = config
+        .get::<String>("stasis.lock_check_command")
+        .or_else(|_| config.get::<String>("stasis.lock-check-command"))
+        .ok();
+
+    let event_log = config
+        .get::<String>("stasis.event_log")
+        .or_else(|_| config.get::<String>("stasis.event-log"))
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| match (p.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(p),
+        });
+
+    let suspend_if_sessions = default_if_missing(
+        config
+            .get::<bool>("stasis.suspend_if_sessions")
+            .or_else(|_| config.get::<bool>("stasis.suspend-if-sessions")),
+        "stasis.suspend_if_sessions",
+        true,
+    )?;
+
+    let max_inhibit_seconds = config
+        .get::<u64>("stasis.max_inhibit_seconds")
+        .or_else(|_| config.get::<u64>("stasis.max-inhibit-seconds"))
+        .ok()
+        .filter(|secs| *secs > 0);
+
+    let battery_critical = parse_battery_critical(config, &templates)?;
+
+    let lock_before_suspend = default_if_missing(
+        config
+            .get::<bool>("stasis.lock_before_suspend")
+            .or_else(|_| config.get::<bool>("stasis.lock-before-suspend")),
+        "stasis.lock_before_suspend",
+        false,
+    )?;
+
+    let brightness_backend = match config
+        .get::<String>("stasis.brightness_backend")
+        .or_else(|_| config.get::<String>("stasis.brightness-backend"))
+        .ok()
+    {
+        None => BrightnessBackend::Auto,
+        Some(s) => match s.trim() {
+            "auto" => BrightnessBackend::Auto,
+            "sysfs" => BrightnessBackend::Sysfs,
+            "brightnessctl" => BrightnessBackend::Brightnessctl,
+            "none" => BrightnessBackend::None,
+            other => {
+                return Err(ConfigError::InvalidValue {
+                    key: "stasis.brightness_backend".to_string(),
+                    message: format!("'{}' is not one of auto, sysfs, brightnessctl, none", other),
+                });
+            }
+        },
+    };
+
+    let suspend_countdown_seconds = config
+        .get::<u64>("stasis.suspend_countdown_seconds")
+        .or_else(|_| config.get::<u64>("stasis.suspend-countdown-seconds"))
+        .unwrap_or(0);
+
+    let restore_on_error = default_if_missing(
+        config
+            .get::<bool>("stasis.restore_on_error")
+            .or_else(|_| config.get::<bool>("stasis.restore-on-error")),
+        "stasis.restore_on_error",
+        true,
+    )?;
+
+    let resume_sequential = default_if_missing(
+        config
+            .get::<bool>("stasis.resume_sequential")
+            .or_else(|_| config.get::<bool>("stasis.resume-sequential")),
+        "stasis.resume_sequential",
+        false,
+    )?;
+
+    let power_debounce_seconds = default_if_missing(
+        config
+            .get::<u64>("stasis.power_debounce_seconds")
+            .or_else(|_| config.get::<u64>("stasis.power-debounce-seconds")),
+        "stasis.power_debounce_seconds",
+        0,
+    )?;
+
+    let skip_display_while_locked = default_if_missing(
+        config
+            .get::<bool>("stasis.skip_display_while_locked")
+            .or_else(|_| config.get::<bool>("stasis.skip-display-while-locked")),
+        "stasis.skip_display_while_locked",
+        false,
+    )?;
+
     let laptop = is_laptop();    
+    // Drop-ins only contribute actions; they use the main file's templates and
+    // ordering, which runs once they are merged so their actions fall in place
+    let block = |path: &str, prefix: &str| -> Result<Vec<IdleActionBlock>, ConfigError> {
+        let mut actions = collect_actions(config, path, prefix, &templates)?;
+        for drop_in in drop_ins {
+            merge_actions(&mut actions, collect_actions(drop_in, path, prefix, &templates)?);
+        }
+        order_actions(config, path, actions)
+    };
     let actions = if laptop {
-        let mut all = Vec::new();
-        all.extend(
-            collect_actions(&config, "stasis.on_ac")
-                .or_else(|_| collect_actions(&config, "stasis.on-ac"))?
-        );
-        all.extend(
-            collect_actions(&config, "stasis.on_battery")
-                .or_else(|_| collect_actions(&config, "stasis.on-battery"))?
-        );
+        let mut all = block("stasis.on_ac", "ac.")?;
+        all.extend(block("stasis.on_battery", "battery.")?);
         all
     } else {
-        collect_actions(&config, "stasis")?
+        block("stasis", "")?
     };
 
     if actions.is_empty() {
-        return Err(eyre!("no valid idle actions found in config"));
+        return Err(ConfigError::NoActions);
+    }
+
+    // Named profiles (`profile:` > `<name>:`), switched to with `set-profile`
+    // regardless of the power source
+    let mut profiles = BTreeMap::new();
+    for name in config.get_keys("stasis.profile").unwrap_or_default() {
+        let path = format!("stasis.profile.{}", name);
+        let profile_actions = order_actions(
+            config,
+            &path,
+            collect_actions(config, &path, &format!("profile.{}.", name), &templates)?,
+        )?;
+        if profile_actions.is_empty() {
+            log_error_message(&format!("Warning: profile '{}' has no actions", name));
+        }
+        profiles.insert(name, profile_actions);
+    }
+
+    // manual_only actions stay out of the timed sequences; `trigger` finds them here
+    let (mut manual_actions, actions): (Vec<_>, Vec<_>) = actions.into_iter().partition(|a| a.manual_only);
+    for profile_actions in profiles.values_mut() {
+        let (manual, timed): (Vec<_>, Vec<_>) = std::mem::take(profile_actions).into_iter().partition(|a| a.manual_only);
+        manual_actions.extend(manual);
+        *profile_actions = timed;
     }
 
     log_message("Parsed Config:");
     log_message(&format!("  pre_suspend_command = {:?}", pre_suspend_command));
+    log_message(&format!("  pre_suspend_settle_ms = {:?}", pre_suspend_settle_ms));
+    log_message(&format!("  on_lock_command = {:?}", on_lock_command));
+    log_message(&format!("  on_unlock_command = {:?}", on_unlock_command));
+    log_message(&format!("  on_power_change = {:?}", on_power_change));
+    log_message(&format!("  templates = {:?}", templates));
     log_message(&format!("  monitor_media = {:?}", monitor_media));
     log_message(&format!("  ignore_remote_media = {:?}", ignore_remote_media));
     log_message(&format!(
         "  respect_wayland_inhibitors = {:?}",
         respect_wayland_inhibitors
     ));
-    log_message(&format!("  debounce_seconds = {:?}", debounce_seconds));
+    log_message(&format!("  debounce = {:?}", debounce));
+    log_message(&format!("  trigger_instant_on_startup = {:?}", trigger_instant_on_startup));
     log_message(&format!("  lid_close_action = {:?}", lid_close_action));
     log_message(&format!("  lid_open_action = {:?}", lid_open_action));
+    log_message(&format!("  log_level = {:?}", log_level));
+    log_message(&format!("  metrics_listen = {:?}", metrics_listen));
     log_message(&format!(
         "  inhibit_apps = [{}]",
         inhibit_apps
@@ -231,14 +1209,84 @@ pub fn load_config(path: &str) -> Result<StasisConfig> {
             .collect::<Vec<_>>()
             .join(", ")
     ));   
+    for (name, patterns) in [("inhibit_app_ids", &inhibit_app_ids), ("inhibit_titles", &inhibit_titles)] {
+        log_message(&format!(
+            "  {} = [{}]",
+            name,
+            patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    log_message(&format!("  inhibit_fullscreen = {:?}", inhibit_fullscreen));
+    log_message(&format!("  ignore_input_devices = {:?}", ignore_input_devices));
+    log_message(&format!("  min_activity_movement = {:?}", min_activity_movement));
+    log_message(&format!("  action_dispatch = {:?}", action_dispatch));
+    log_message(&format!("  relock_grace_seconds = {:?}", relock_grace_seconds));
+    log_message(&format!(
+        "  ignored_players = [{}]",
+        ignored_players
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    log_message(&format!("  lock_then_suspend_after = {:?}", lock_then_suspend_after));
+    log_message(&format!("  manage_brightness = {:?}", manage_brightness));
+    log_message(&format!("  reset_idle_on_media_stop = {:?}", reset_idle_on_media_stop));
+    log_message(&format!("  network_activity_inhibit = {:?}", network_activity_inhibit));
+    log_message(&format!("  network_activity_threshold_kbps = {:?}", network_activity_threshold_kbps));
+    log_message(&format!("  inhibit_above_load = {:?}", inhibit_above_load));
+    log_message(&format!("  inhibit_processes = {:?}", inhibit_processes));
+    log_message(&format!("  inhibit_process_interval = {:?}", inhibit_process_interval));
+    log_message(&format!("  action_shell = {:?}", action_shell));
+    log_message(&format!("  activity_debounce_ms = {:?}", activity_debounce_ms));
+    log_message(&format!("  lock_check_command = {:?}", lock_check_command));
+    log_message(&format!("  event_log = {:?}", event_log));
+    log_message(&format!("  suspend_if_sessions = {:?}", suspend_if_sessions));
+    log_message(&format!("  max_inhibit_seconds = {:?}", max_inhibit_seconds));
+    log_message(&format!("  battery_critical = {:?}", battery_critical));
+    log_message(&format!("  lock_before_suspend = {:?}", lock_before_suspend));
+    log_message(&format!("  brightness_backend = {:?}", brightness_backend));
+    log_message(&format!("  suspend_countdown_seconds = {:?}", suspend_countdown_seconds));
+    log_message(&format!("  restore_on_error = {:?}", restore_on_error));
+    log_message(&format!("  resume_sequential = {:?}", resume_sequential));
+    log_message(&format!("  power_debounce_seconds = {:?}", power_debounce_seconds));
+    log_message(&format!("  skip_display_while_locked = {:?}", skip_display_while_locked));
     log_message("  actions:");
-    for action in &actions {
+    for action in actions.iter().chain(profiles.values().flatten()).chain(&manual_actions) {
         let mut details = format!(
-            "    {}: timeout={}s, command=\"{}\"",
-            action.name, action.timeout, action.command
+            "    {}: kind={}, timeout={}s, command=\"{}\"",
+            action.name, action.kind, action.timeout, action.command
         );
-        if let Some(resume_cmd) = &action.resume_command {
-            details.push_str(&format!(", resume_command=\"{}\"", resume_cmd));
+        if action.has_resume_command() {
+            details.push_str(&format!(", resume_command=\"{}\"", action.resume_commands.join("\" then \"")));
+            if action.also_run_on_activity {
+                details.push_str(" (also on activity)");
+            }
+        }
+        if let Some(cmd) = &action.command_x11 {
+            details.push_str(&format!(", command_x11=\"{}\"", cmd));
+        }
+        if let Some(cmd) = &action.command_wayland {
+            details.push_str(&format!(", command_wayland=\"{}\"", cmd));
+        }
+        if action.once_per_boot {
+            details.push_str(", once_per_boot");
+        }
+        if action.manual_only {
+            details.push_str(", manual_only");
+        }
+        if !action.relative {
+            details.push_str(", relative=false");
+        }
+        if let Some(required) = &action.requires {
+            details.push_str(&format!(", requires={}", required));
+        }
+        if action.verify {
+            details.push_str(", verify");
+        }
+        if !action.env.is_empty() {
+            let vars: Vec<&str> = action.env.iter().map(|(k, _)| k.as_str()).collect();
+            details.push_str(&format!(", env=[{}]", vars.join(",")));
         }
         log_message(&details);
     }
@@ -246,12 +1294,83 @@ pub fn load_config(path: &str) -> Result<StasisConfig> {
     Ok(StasisConfig {
         actions,
         pre_suspend_command,
+        pre_suspend_settle_ms,
         monitor_media,
         ignore_remote_media,
         respect_wayland_inhibitors,
         inhibit_apps,
-        debounce_seconds,
+        inhibit_app_ids,
+        inhibit_titles,
+        inhibit_fullscreen,
+        debounce,
         lid_close_action,
         lid_open_action,
+        log_level,
+        metrics_listen,
+        trigger_instant_on_startup,
+        ignore_input_devices,
+        min_activity_movement,
+        on_lock_command,
+        on_unlock_command,
+        on_power_change,
+        action_dispatch,
+        relock_grace_seconds,
+        ignored_players,
+        lock_then_suspend_after,
+        manage_brightness,
+        reset_idle_on_media_stop,
+        network_activity_inhibit,
+        network_activity_threshold_kbps,
+        inhibit_above_load,
+        inhibit_processes,
+        inhibit_process_interval,
+        action_shell,
+        activity_debounce_ms,
+        lock_check_command,
+        profiles,
+        manual_actions,
+        event_log,
+        suspend_if_sessions,
+        max_inhibit_seconds,
+        battery_critical,
+        lock_before_suspend,
+        brightness_backend,
+        suspend_countdown_seconds,
+        restore_on_error,
+        resume_sequential,
+        power_debounce_seconds,
+        skip_display_while_locked,
     })
 }
+
+/// `load_config` for the binary, which only reports errors
+pub fn load_config_report(path: &str) -> eyre::Result<StasisConfig> {
+    load_config(path).map_err(eyre::Report::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The given actions at the top level and under on_ac/on_battery, so the
+    /// same block is read whether the host looks like a laptop or not
+    fn rune(actions: &str) -> RuneConfig {
+        let nested: String = actions.lines().map(|l| format!("  {}\n", l)).collect();
+        let config = format!("stasis:\n{}  on_ac:\n{}  end\n  on_battery:\n{}  end\nend\n", actions, nested, nested);
+        RuneConfig::from_str(&config).expect("test config parses")
+    }
+
+    #[test]
+    fn drop_in_action_is_ordered_with_the_main_block() {
+        let main = rune(
+            "  actions_order [\"dim\" \"lock_screen\" \"suspend\"]\n  \
+             dim:\n    timeout 60\n    command \"true\"\n  end\n  \
+             suspend:\n    timeout 600\n    command \"true\"\n  end\n",
+        );
+        let drop_in = rune("  lock_screen:\n    timeout 300\n    command \"true\"\n  end\n");
+
+        let cfg = parse_config(&main, &[drop_in]).expect("merged config parses");
+        let names: Vec<&str> = cfg.actions.iter().take(3).map(|a| a.base_name()).collect();
+        assert_eq!(names, ["dim", "lock_screen", "suspend"]);
+    }
+}
@@ -0,0 +1,103 @@
+use super::keys::GENERAL_KEYS;
+
+/// A commented default config covering every supported key, for
+/// `stasis --print-default-config`
+pub fn default_config_template() -> String {
+    let mut out = String::new();
+    out.push_str("@description \"Stasis configuration file\"\n\n");
+    out.push_str("stasis:\n");
+
+    for k in GENERAL_KEYS {
+        out.push_str(&format!("  # {}\n", k.comment));
+        let prefix = if k.enabled { "" } else { "# " };
+        out.push_str(&format!("  {}{} {}\n\n", prefix, k.key, k.value));
+    }
+
+    out.push_str(
+        "  # commands run when the power source changes (laptops)\n\
+         \x20 # on_power_change:\n\
+         \x20 #   to_ac_command \"notify-send 'On AC'\"\n\
+         \x20 #   to_battery_command \"notify-send 'On battery'\"\n\
+         \x20 # end\n\n",
+    );
+
+    out.push_str(
+        "  # named action sets for `stasis set-profile <name>` (`none` switches back)\n\
+         \x20 # profile:\n\
+         \x20 #   presentation:\n\
+         \x20 #     lock_screen:\n\
+         \x20 #       timeout 3600\n\
+         \x20 #       command \"swaylock\"\n\
+         \x20 #     end\n\
+         \x20 #   end\n\
+         \x20 # end\n\n",
+    );
+
+    out.push_str(
+        "  # reusable commands, referenced as command \"@locker\" (also custom:@name lid actions)\n\
+         \x20 # templates:\n\
+         \x20 #   locker \"swaylock -f\"\n\
+         \x20 # end\n\n",
+    );
+
+    out.push_str(
+        "  # run critical_action once the battery discharges below critical_percent,\n\
+         \x20 # even while in use; armed again at rearm_percent (default +5)\n\
+         \x20 # battery:\n\
+         \x20 #   critical_percent 5\n\
+         \x20 #   critical_action \"systemctl hibernate\"\n\
+         \x20 #   rearm_percent 10\n\
+         \x20 # end\n\n",
+    );
+
+    out.push_str(
+        "  # idle actions fire in order; each timeout (seconds) counts from the previous\n\
+         \x20 # action. timeout 0 makes an instant action, fired at startup.\n\
+         \x20 # laptops read them from on_ac: / on_battery: blocks instead.\n\
+         \x20 # per-action keys:\n\
+         \x20 #   kind              brightness | dpms | lock_screen | suspend | custom (default: from the name)\n\
+         \x20 #   resume_command    run after a system resume ($STASIS_IDLE_SECONDS = time away); may be a list\n\
+         \x20 #   also_run_on_activity  true also runs resume_command when the user returns from idle\n\
+         \x20 #                     (default: only after a system resume)\n\
+         \x20 #   once_per_boot     fire at most once per boot\n\
+         \x20 #   manual_only       never fire on a timeout, only via `stasis trigger <name>`\n\
+         \x20 #   relative          false counts the timeout from the last activity\n\
+         \x20 #   requires          name of an action that must have fired first\n\
+         \x20 #   active_from / active_until  only fire between these local times (HH:MM)\n\
+         \x20 #   verify            check a lock_screen/dpms action took effect, log if not\n\
+         \x20 #   env: ... end      extra environment variables for the action's commands\n\
+         \x20 #   working_directory  run the commands from here (~ and $VARS expanded)\n\
+         \x20 #   command_x11 / command_wayland  replace command per session type\n\
+         \x20 # or list them in a chain: block, where `after` (seconds or \"5m\") counts\n\
+         \x20 # from the previous step; a block has either a chain or flat actions.\n\
+         \x20 # chain:\n\
+         \x20 #   dpms:\n\
+         \x20 #     after \"5m\"\n\
+         \x20 #     command \"wlopm --off '*'\"\n\
+         \x20 #   end\n\
+         \x20 #   suspend:\n\
+         \x20 #     after \"25m\"\n\
+         \x20 #     command \"systemctl suspend\"\n\
+         \x20 #   end\n\
+         \x20 # end\n",
+    );
+    out.push_str(
+        "  lock_screen:\n\
+         \x20   timeout 300\n\
+         \x20   command \"swaylock\"\n\
+         \x20 end\n\n\
+         \x20 dpms:\n\
+         \x20   timeout 60\n\
+         \x20   command \"wlopm --off '*'\"\n\
+         \x20   resume_command \"wlopm --on '*'\"\n\
+         \x20   also_run_on_activity true\n\
+         \x20 end\n\n\
+         \x20 suspend:\n\
+         \x20   timeout 1800\n\
+         \x20   command \"systemctl suspend\"\n\
+         \x20   requires \"lock_screen\"\n\
+         \x20 end\n",
+    );
+    out.push_str("end\n");
+    out
+}
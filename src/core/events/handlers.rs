@@ -1,11 +1,14 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 
-use crate::{config::model::{IdleAction, LidCloseAction, LidOpenAction}, core::manager::{helpers::{run_action, wake_idle_tasks}, Manager}};
-use crate::log::log_message;
+use crate::{config::model::{IdleAction, LidCloseAction, LidOpenAction}, core::manager::{helpers::{run_action, wake_idle_tasks}, wait_for_lock, InhibitReason, Manager, LOCK_WAIT_SECS}};
+use crate::log::{log_message, log_trace_message};
 
 pub enum Event {
-    InputActivity,
+    /// Activity from a libinput device, identified by its name
+    InputActivity { device: String },
+    /// Network throughput above `network_activity_threshold_kbps`
+    NetworkActivity { kbps: u64 },
     MediaPlaybackActive,
     MediaPlaybakcEnded,
     ACConnected,
@@ -20,17 +23,28 @@ pub enum Event {
 
 pub async fn handle_event(manager: &Arc<Mutex<Manager>>, event: Event) {
     match event {
-        Event::InputActivity => {
+        Event::InputActivity { device } => {
+            log_trace_message(&format!("Input activity from '{}'", device));
             let mut mgr = manager.lock().await;
-            mgr.reset().await;
+            mgr.register_activity().await;
             mgr.state.lock_notify.notify_waiters();
             wake_idle_tasks(&mgr.state);
         }
         
+        Event::NetworkActivity { kbps } => {
+            log_trace_message(&format!("Network activity: {} kbps", kbps));
+            let mut mgr = manager.lock().await;
+            mgr.reset().await;
+            mgr.state.lock_notify.notify_waiters();
+            wake_idle_tasks(&mgr.state);
+        }
+
         Event::ACConnected => {
             let mut mgr = manager.lock().await;
             if mgr.state.is_laptop() {
-                mgr.state.set_on_battery(false);
+                if mgr.observe_power_source(false).await {
+                    log_message("Power source changed: AC");
+                }
                 wake_idle_tasks(&mgr.state);
             }
         }
@@ -38,26 +52,26 @@ pub async fn handle_event(manager: &Arc<Mutex<Manager>>, event: Event) {
         Event::ACDisconnected => {
             let mut mgr = manager.lock().await;
             if mgr.state.is_laptop() {
-                mgr.state.set_on_battery(true);
+                if mgr.observe_power_source(true).await {
+                    log_message("Power source changed: Battery");
+                }
                 wake_idle_tasks(&mgr.state);
             }
         }
          
         Event::Suspend => {
             let mut mgr = manager.lock().await;
-            mgr.pause(false).await;
             mgr.trigger_pre_suspend(false).await;
         }
         
         Event::Resume => {
-            let mut mgr = manager.lock().await;
-            mgr.resume(false).await;
+            let mgr = manager.lock().await;
             wake_idle_tasks(&mgr.state);
         }
 
         Event::Wake => {
             let mut mgr = manager.lock().await;
-            mgr.resume(false).await;
+            mgr.fire_resume_queue(true).await;
             mgr.reset().await;
             wake_idle_tasks(&mgr.state);
         }
@@ -70,13 +84,13 @@ pub async fn handle_event(manager: &Arc<Mutex<Manager>>, event: Event) {
 
         Event::MediaPlaybackActive => {
             let mut mgr = manager.lock().await;
-            mgr.pause(false).await;
+            mgr.pause(InhibitReason::Media(None)).await;
             wake_idle_tasks(&mgr.state)
         }
 
         Event::MediaPlaybakcEnded => {
             let mut mgr = manager.lock().await;
-            mgr.resume(false).await;
+            mgr.media_stopped().await;
             wake_idle_tasks(&mgr.state);
         }
 
@@ -92,11 +106,19 @@ pub async fn handle_event(manager: &Arc<Mutex<Manager>>, event: Event) {
 
                 match lid_close {
                     LidCloseAction::Suspend => {
+                        // Wait for the locker with the manager released
+                        if mgr.start_lock_before_suspend().await {
+                            drop(mgr);
+                            if !wait_for_lock(manager, Duration::from_secs(LOCK_WAIT_SECS)).await {
+                                log_message("Lock screen did not come up in time, suspending anyway");
+                            }
+                            mgr = manager.lock().await;
+                        }
                         mgr.trigger_pre_suspend(true).await;
                     }
                     LidCloseAction::LockScreen => {
                         if let Some(lock_action) = lock_action_opt {
-                            run_action(&mut mgr, &lock_action).await;
+                            run_action(&mut mgr, &lock_action, false).await;
                         }
                     }
                     LidCloseAction::Custom(cmd) => {
@@ -117,7 +139,6 @@ pub async fn handle_event(manager: &Arc<Mutex<Manager>>, event: Event) {
             if let Some(cfg) = &mgr.state.cfg {
                 match &cfg.lid_open_action {
                     LidOpenAction::Wake => {
-                        mgr.resume(false).await;
                         mgr.reset().await;
                         wake_idle_tasks(&mgr.state);
                     }
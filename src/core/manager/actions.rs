@@ -1,11 +1,140 @@
 use std::time::Duration;
 use eyre::Result;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
+use std::path::PathBuf;
 use std::process::Stdio;
 
-use crate::config::model::{IdleActionBlock, IdleAction};
+use crate::config::model::{ActionShell, IdleActionBlock, IdleAction};
 use crate::log::log_message;
 
+/// Split a command line into arguments like a shell would, without expanding
+/// anything: whitespace separates words, single quotes are literal, double
+/// quotes allow `\"` and `\\`, and a backslash outside quotes escapes the next
+/// character. None for an unterminated quote.
+pub fn split_command(cmd: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = cmd.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => current.push(c),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\') => current.push(c),
+                            c => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                        },
+                        c => current.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                current.push(chars.next()?);
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        args.push(current);
+    }
+    Some(args)
+}
+
+/// A `Command` for `cmd` according to `action_shell`, with the options' environment
+/// and working directory. Fails if that directory doesn't exist, rather than
+/// running the command somewhere else
+fn build_command(cmd: &str, opts: &CommandOptions) -> std::io::Result<Command> {
+    let mut command = match &opts.shell {
+        ActionShell::Shell(shell) => {
+            let mut command = Command::new(shell);
+            command.arg("-c").arg(cmd);
+            command
+        }
+        ActionShell::None => {
+            let argv = split_command(cmd).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "unterminated quote in command")
+            })?;
+            let (program, args) = argv.split_first().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty command")
+            })?;
+            let mut command = Command::new(program);
+            command.args(args);
+            command
+        }
+    };
+    command
+        .envs(std::env::vars())
+        .envs(opts.env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null());
+    if let Some(dir) = &opts.working_directory {
+        if !dir.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("working directory '{}' does not exist", dir.display()),
+            ));
+        }
+        command.current_dir(dir);
+    }
+    Ok(command)
+}
+
+/// Environment variable giving resume and unlock hooks how long the user was
+/// away, in whole seconds
+pub const IDLE_SECONDS_ENV: &str = "STASIS_IDLE_SECONDS";
+
+/// Per-action settings applied when spawning its commands
+#[derive(Debug, Clone, Default)]
+pub struct CommandOptions {
+    /// `action_shell` of the loaded config
+    pub shell: ActionShell,
+    /// Extra environment variables, set on top of the inherited environment
+    pub env: Vec<(String, String)>,
+    /// Kill the process when the task waiting on it is aborted, so a superseded
+    /// command (e.g. a brightness fade) stops instead of running on
+    pub kill_on_drop: bool,
+    /// Run in this directory instead of the daemon's
+    pub working_directory: Option<PathBuf>,
+}
+
+impl CommandOptions {
+    /// Options for a command that belongs to no action (hooks): just the shell
+    pub fn with_shell(shell: ActionShell) -> Self {
+        Self { shell, ..Default::default() }
+    }
+
+    /// Add `STASIS_IDLE_SECONDS` for a resume-type hook
+    pub fn with_idle_seconds(mut self, idle: Duration) -> Self {
+        self.env.push((IDLE_SECONDS_ENV.to_string(), idle.as_secs().to_string()));
+        self
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ActionRequest {
     RunCommand(String),
@@ -16,7 +145,7 @@ pub enum ActionRequest {
 
 /// Prepare action for execution, similar to old logic
 pub async fn prepare_action(action: &IdleActionBlock) -> Vec<ActionRequest> {
-    let cmd = action.command.clone();
+    let cmd = action.session_command().to_string();
 
     match action.kind {
         IdleAction::Suspend => {
@@ -29,13 +158,11 @@ pub async fn prepare_action(action: &IdleActionBlock) -> Vec<ActionRequest> {
         }
 
         IdleAction::LockScreen => {
-            let probe_cmd = &action.command;
-
-            if is_process_running(probe_cmd).await {
+            if is_process_running(&cmd).await {
                 log_message("Lockscreen already running, skipping action.");
-                vec![ActionRequest::Skip(probe_cmd.to_string())]
+                vec![ActionRequest::Skip(cmd)]
             } else {
-                vec![ActionRequest::RunCommand(action.command.clone())]
+                vec![ActionRequest::RunCommand(cmd)]
             }
         }
 
@@ -49,17 +176,16 @@ pub async fn prepare_action(action: &IdleActionBlock) -> Vec<ActionRequest> {
     }
 }
 
-/// Run a shell command silently (log to /tmp/stasis.log)
-pub async fn run_command_silent(cmd: &str) -> Result<()> {
+/// Run a command silently (output appended to /tmp/stasis.log)
+pub async fn run_command_silent_with(cmd: &str, opts: &CommandOptions) -> Result<()> {
     let log_file = "/tmp/stasis.log";
     let fut = async {
-        let mut child = Command::new("sh")
-            .arg("-c")
-            .arg(format!("{cmd} >> {log_file} 2>&1"))
-            .envs(std::env::vars())
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+        // Output goes to the log file, opened here so it works without a shell too
+        let log = std::fs::OpenOptions::new().create(true).append(true).open(log_file)?;
+        let mut child = build_command(cmd, opts)?
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .kill_on_drop(opts.kill_on_drop)
             .spawn()?;
 
         let status = child.wait().await?;
@@ -73,24 +199,47 @@ pub async fn run_command_silent(cmd: &str) -> Result<()> {
     Ok(())
 }
 
-/// Run a command detached (e.g., lock screen) and return its PID
-pub async fn run_command_detached(command: &str) -> Result<u32, Box<dyn std::error::Error>> {
+/// Exit status and stderr of a command run by `run_command_captured`
+#[derive(Debug)]
+pub struct CapturedOutput {
+    /// None when the command was killed by a signal
+    pub code: Option<i32>,
+    pub stderr: String,
+}
+
+/// Run a command to completion and capture its stderr. Only used by
+/// `test-action`; actions themselves stay fire-and-forget. A command still
+/// running after `timeout` is left alone (it may be a screen locker) and
+/// reported as an error.
+pub async fn run_command_captured(cmd: &str, opts: &CommandOptions, timeout: Duration) -> Result<CapturedOutput> {
+    let child = build_command(cmd, opts)?
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| eyre::eyre!("Command '{}' still running after {}s", cmd, timeout.as_secs()))??;
+    Ok(CapturedOutput {
+        code: output.status.code(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim_end().to_string(),
+    })
+}
+
+/// Run a command detached (e.g., lock screen) and return its `Child`. Dropping
+/// the handle leaves the process running (tokio reaps it); keeping it allows
+/// `try_wait`/`kill` and the real exit status.
+pub async fn run_command_detached_with(command: &str, opts: &CommandOptions) -> Result<Child, Box<dyn std::error::Error>> {
     let parts: Vec<&str> = command.split_whitespace().collect();
     if parts.is_empty() {
         return Err("Empty command".into());
     }
 
-    let child = Command::new("sh")
-        .arg("-c")
-        .arg(command)
-        .envs(std::env::vars())
-        .stdin(Stdio::null())
+    let child = build_command(command, opts)?
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()?;
-
-    let pid = child.id().ok_or("Failed to get child PID")?;
-    Ok(pid)
+    Ok(child)
 }
 
 /// Check if a process matching `cmd` is running (by name)
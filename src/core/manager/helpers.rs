@@ -1,16 +1,19 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tokio::process::Command;
 
 
-use crate::log::{log_error_message, log_message};
+use crate::log::{log_debug_message, log_error_message, log_message};
 
 use crate::{
-    config::model::IdleActionBlock, 
+    config::model::{ActionDispatch, BrightnessBackend, IdleAction, IdleActionBlock}, 
     core::manager::{
-        actions::{is_process_running, prepare_action, run_command_detached, run_command_silent, ActionRequest}, 
-        state::ManagerState, Manager,
-    }
+        actions::{is_process_running, prepare_action, run_command_detached_with, run_command_silent_with, ActionRequest, CommandOptions}, 
+        state::ManagerState, tasks::spawn_task_limited, InhibitReason, Manager,
+    },
+    core::services::dbus::{remote_sessions, session_locked_hint},
+    core::utils::{display_available, mark_spent_this_boot, runtime_dir, spent_this_boot},
 };
 
 // Brightness
@@ -21,46 +24,135 @@ struct BrightnessState {
     device: String,
 }
 
+/// How brightness is actually captured and restored, resolved from
+/// `brightness_backend`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrightnessMethod {
+    Sysfs,
+    Brightnessctl,
+    /// `brightness_backend "none"` or `manage_brightness false`
+    Off,
+    /// `auto` found no working method; brightness actions are skipped so a
+    /// dim is never left without a way back
+    Unavailable,
+}
+
+/// What `auto` finds: a readable backlight, else a working brightnessctl
+async fn probe_brightness() -> BrightnessMethod {
+    if capture_sysfs_brightness().is_some() {
+        return BrightnessMethod::Sysfs;
+    }
+    let works = Command::new("brightnessctl")
+        .arg("get")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|s| s.success());
+    if works { BrightnessMethod::Brightnessctl } else { BrightnessMethod::Unavailable }
+}
+
+/// Method in use; `Off` until `set_brightness_backend` has run
+fn brightness_method(state: &ManagerState) -> BrightnessMethod {
+    state.brightness_method.unwrap_or(BrightnessMethod::Off)
+}
+
+/// Resolve the config's `brightness_backend` into the state, at startup and on
+/// reload. `auto` is probed the first time only; later reloads reuse the result.
+/// Logged only when the method changes, so a reload stays quiet.
+pub async fn set_brightness_backend(state: &mut ManagerState) {
+    let Some(cfg) = state.cfg.clone() else {
+        return;
+    };
+    let method = match cfg.brightness_backend {
+        _ if !cfg.manage_brightness => BrightnessMethod::Off,
+        BrightnessBackend::Sysfs => BrightnessMethod::Sysfs,
+        BrightnessBackend::Brightnessctl => BrightnessMethod::Brightnessctl,
+        BrightnessBackend::None => BrightnessMethod::Off,
+        BrightnessBackend::Auto => match state.brightness_probe {
+            Some(probed) => probed,
+            None => *state.brightness_probe.insert(probe_brightness().await),
+        },
+    };
+    if state.brightness_method == Some(method) {
+        return;
+    }
+    state.brightness_method = Some(method);
+    match method {
+        BrightnessMethod::Sysfs => log_message("Brightness backend: sysfs"),
+        BrightnessMethod::Brightnessctl => log_message("Brightness backend: brightnessctl"),
+        BrightnessMethod::Off => log_message("Brightness capture and restore disabled"),
+        BrightnessMethod::Unavailable => log_error_message(
+            "Warning: no brightness method available (no readable backlight, brightnessctl \
+             missing or failing); brightness actions are disabled",
+        ),
+    }
+}
+
+/// True when brightness actions must be skipped because they could not be undone
+pub fn brightness_unavailable(state: &ManagerState) -> bool {
+    brightness_method(state) == BrightnessMethod::Unavailable
+}
+
 pub async fn capture_brightness(state: &mut ManagerState) -> Result<(), std::io::Error> {
-    // Try sysfs method first
-    if let Some(sys_brightness) = capture_sysfs_brightness() {
-        log_message(&format!("Captured brightness via sysfs: {}", sys_brightness.value));
+    let method = brightness_method(state);
+    let value = match method {
+        BrightnessMethod::Sysfs => capture_sysfs_brightness().map(|b| b.value),
+        BrightnessMethod::Brightnessctl => brightnessctl_get().await,
+        BrightnessMethod::Off | BrightnessMethod::Unavailable => return Ok(()),
+    };
 
-        // Convert safely to u8
-        state.previous_brightness = Some(sys_brightness.value.min(u8::MAX as u32) as u8);
-        return Ok(());
+    match value {
+        Some(value) => {
+            log_message(&format!("Captured brightness via {:?}: {}", method, value));
+            // Convert safely to u8
+            state.previous_brightness = Some(value.min(u8::MAX as u32) as u8);
+            save_brightness_state(state);
+        }
+        // Nothing recorded means nothing is restored, never a guessed (black) level
+        None => log_error_message(&format!("Failed to capture brightness via {:?}, it will not be restored", method)),
     }
+    Ok(())
+}
 
-    // Fallback to brightnessctl
-    log_message("Falling back to brightnessctl for brightness capture");
+/// `brightnessctl get`, None when it fails or prints something unexpected
+async fn brightnessctl_get() -> Option<u32> {
     match Command::new("brightnessctl").arg("get").output().await {
-        Ok(out) if out.status.success() => {
-            let val = String::from_utf8_lossy(&out.stdout)
-                .trim()
-                .parse::<u32>()
-                .unwrap_or(0);
-            state.previous_brightness = Some(val.min(u8::MAX as u32) as u8);
-            log_message(&format!("Captured brightness via brightnessctl: {}", val));
-        }
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).trim().parse().ok(),
         Ok(out) => {
             log_error_message(&format!("brightnessctl get failed: {:?}", out.status));
+            None
         }
         Err(e) => {
             log_error_message(&format!("Failed to execute brightnessctl: {}", e));
+            None
         }
     }
-
-    Ok(())
 }
+
 pub async fn restore_brightness(state: &mut ManagerState) -> Result<(), std::io::Error> {
     if let Some(level) = state.previous_brightness {
-        log_message(&format!("Attempting to restore brightness to {}", level));
+        set_brightness(state, level as u32).await;
+
+        // Reset stored brightness
+        state.previous_brightness = None;
+        clear_brightness_state();
+    }
+    Ok(())
+}
 
-        // Try sysfs restore first
-        if restore_sysfs_brightness(level as u32).is_ok() {
-            log_message("Brightness restored via sysfs");
-        } else {
-            log_message("Falling back to brightnessctl for brightness restore");
+/// Every backlight write goes through here, after any in-flight brightness
+/// command has been stopped
+async fn set_brightness(state: &mut ManagerState, level: u32) {
+    state.cancel_brightness_task().await;
+    log_message(&format!("Attempting to restore brightness to {}", level));
+
+    match brightness_method(state) {
+        BrightnessMethod::Sysfs => match restore_sysfs_brightness(level) {
+            Ok(()) => log_message("Brightness restored via sysfs"),
+            Err(e) => log_error_message(&format!("Failed to restore brightness via sysfs: {}", e)),
+        },
+        BrightnessMethod::Brightnessctl => {
             if let Err(e) = Command::new("brightnessctl")
                 .arg("set")
                 .arg(level.to_string())
@@ -70,11 +162,46 @@ pub async fn restore_brightness(state: &mut ManagerState) -> Result<(), std::io:
                 log_error_message(&format!("Failed to restore brightness: {}", e));
             }
         }
+        BrightnessMethod::Off | BrightnessMethod::Unavailable => {
+            log_message("No brightness method, not restoring");
+        }
+    }
+}
 
-        // Reset stored brightness
-        state.previous_brightness = None;
+/// Captured brightness persisted while dimmed, so a SIGKILLed instance does not
+/// leave the screen dark: the next startup restores it
+fn brightness_state_path() -> PathBuf {
+    runtime_dir().join("stasis-brightness")
+}
+
+fn save_brightness_state(state: &ManagerState) {
+    if let Some(level) = state.previous_brightness
+        && let Err(e) = fs::write(brightness_state_path(), level.to_string())
+    {
+        log_error_message(&format!("Failed to write brightness state file: {}", e));
     }
-    Ok(())
+}
+
+pub fn clear_brightness_state() {
+    let _ = fs::remove_file(brightness_state_path());
+}
+
+/// Restore brightness left dimmed by a previous instance that died before it
+/// could restore it itself. Call at startup, before any action runs.
+pub async fn restore_crashed_brightness(state: &mut ManagerState) {
+    let path = brightness_state_path();
+    let Ok(content) = fs::read_to_string(&path) else {
+        return;
+    };
+
+    match content.trim().parse::<u32>() {
+        Ok(level) => {
+            log_message(&format!("Found brightness left by a previous instance, restoring {}", level));
+            set_brightness(state, level).await;
+        }
+        Err(_) => log_error_message(&format!("Ignoring malformed brightness state file {}", path.display())),
+    }
+    clear_brightness_state();
 }
 fn capture_sysfs_brightness() -> Option<BrightnessState> {
     let base = Path::new("/sys/class/backlight");
@@ -103,8 +230,11 @@ fn restore_sysfs_brightness(value: u32) -> Result<(), std::io::Error> {
     Ok(())
 }
 
+/// Make the idle loop recompute its deadline. `notify_one` stores a permit when
+/// the loop is not parked yet (e.g. still in `check_timeouts`), so a wakeup sent
+/// during a reload or an event is never lost; `notify_waiters` would drop it.
 pub fn wake_idle_tasks(state: &ManagerState) {
-    state.notify.notify_waiters();
+    state.notify.notify_one();
 }
 
 // Getters and Setters
@@ -112,70 +242,201 @@ pub fn update_lock_state(state: &mut ManagerState, locked: bool) {
     state.lock_state.is_locked = locked;
 }
 
-pub fn get_compositor_manager(state: &mut ManagerState) -> bool {
-    state.compositor_managed
-}
-
-pub fn set_compositor_manager(state: &mut ManagerState, value: bool) {
-    state.compositor_managed = value;
-}
-
 pub fn get_manual_inhibit(state: &mut ManagerState) -> bool {
     state.manually_paused
 }
 
 pub async fn set_manual_inhibit(mgr: &mut Manager, inhibit: bool) {
     if inhibit {
-        mgr.pause(true).await;
+        mgr.pause(InhibitReason::Manual).await;
+    }
+}
+
+/// Checks shared by every way of running an action: display availability for
+/// GUI actions and `once_per_boot`
+fn action_allowed(action: &IdleActionBlock) -> bool {
+    // Lock, DPMS and brightness need a running display server
+    if matches!(action.kind, IdleAction::LockScreen | IdleAction::Dpms | IdleAction::Brightness)
+        && !display_available()
+    {
+        log_error_message(&format!("No display available, skipping action '{}'", action.name));
+        return false;
+    }
+
+    if action.once_per_boot && spent_this_boot(&action.name) {
+        log_message(&format!("Skipping action '{}': already fired this boot", action.name));
+        return false;
+    }
+
+    true
+}
+
+fn mark_once_per_boot(action: &IdleActionBlock) {
+    if action.once_per_boot
+        && let Err(e) = mark_spent_this_boot(&action.name)
+    {
+        log_error_message(&format!("Failed to record once_per_boot marker for '{}': {}", action.name, e));
     }
 }
 
-pub async fn run_action(mgr: &mut Manager, action: &IdleActionBlock) {
+/// Run one action. `manual` is false when it fired on its own (idle timeout,
+/// instant, lock_then_suspend_after) and is passed on to the pre-suspend hook.
+pub async fn run_action(mgr: &mut Manager, action: &IdleActionBlock, manual: bool) {
     log_message(&format!(
         "Action triggered: name=\"{}\" kind={:?} timeout={} command=\"{}\"",
         action.name, action.kind, action.timeout, action.command
     ));
 
+    if !action_allowed(action) {
+        return;
+    }
+
+    if action.kind == IdleAction::Brightness && brightness_unavailable(&mgr.state) {
+        log_debug_message(&format!("Skipping action '{}': no brightness method available", action.name));
+        return;
+    }
+
+    if action.kind == IdleAction::LockScreen && mgr.state.lock_state.disabled {
+        log_message(&format!("Skipping action '{}': lock command disabled after repeated failures", action.name));
+        return;
+    }
+
+    // Guard against double-locking when something else already locked the session
+    if action.kind == IdleAction::LockScreen
+        && let Some(check) = mgr.state.cfg.as_ref().and_then(|c| c.lock_check_command.clone())
+        && run_command_silent_with(&check, &mgr.state.hook_options()).await.is_ok()
+    {
+        log_message(&format!("Skipping action '{}': lock_check_command reports already locked", action.name));
+        return;
+    }
+
+    // Someone else may be using a shared machine over SSH
+    if action.kind == IdleAction::Suspend
+        && mgr.state.cfg.as_ref().is_some_and(|c| !c.suspend_if_sessions)
+    {
+        match remote_sessions().await {
+            Ok(sessions) if !sessions.is_empty() => {
+                log_message(&format!(
+                    "Skipping action '{}': remote session(s) open: {}",
+                    action.name,
+                    sessions.join(", ")
+                ));
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => log_error_message(&format!("Failed to list logind sessions, suspending anyway: {}", e)),
+        }
+    }
+
+    // Lock first and leave the suspend to the lock watcher, which runs it once
+    // the locker is up; the manager isn't held while waiting. Boxed: locking
+    // goes back through run_action
+    if action.kind == IdleAction::Suspend
+        && mgr.state.suspend_after_lock.is_none()
+        && Box::pin(mgr.start_lock_before_suspend()).await
+    {
+        log_message(&format!("Suspend '{}' waits for the lock screen", action.name));
+        mgr.state.suspend_after_lock = Some((action.clone(), manual));
+        return;
+    }
+
+    mgr.state.metrics.record_fired(&action.kind);
+    if !action.is_instant() && mgr.state.idle_since.is_none() {
+        mgr.state.idle_since = Some(Instant::now());
+    }
+    mgr.state.log_event("action", serde_json::json!({
+        "name": action.name,
+        "kind": action.kind.to_string(),
+        "block": mgr.state.active_block_name(),
+        "idle_seconds": mgr.state.last_activity.elapsed().as_secs(),
+    }));
+
     // Brightness capture
-    if matches!(action.kind, crate::config::model::IdleAction::Brightness) && mgr.state.previous_brightness.is_none() {
+    // Capture once per idle session so a re-armed brightness action (power switch,
+    // reload) never records an already-dimmed level as the value to restore
+    let manage_brightness = mgr.state.cfg.as_ref().is_none_or(|c| c.manage_brightness);
+    if matches!(action.kind, crate::config::model::IdleAction::Brightness)
+        && manage_brightness
+        && !mgr.state.active_flags.brightness_captured
+        && mgr.state.previous_brightness.is_none()
+    {
         let _ = capture_brightness(&mut mgr.state).await;
+        mgr.state.active_flags.brightness_captured = true;
     }
 
     if matches!(action.kind, crate::config::model::IdleAction::LockScreen) {
         mgr.state.lock_state.is_locked = true;
+        mgr.state.log_event("lock", serde_json::json!({ "reason": action.name }));
         mgr.state.lock_notify.notify_one();
         log_message("Lock screen action triggered, notifying lock watcher");
     }
 
+    mark_once_per_boot(action);
+
+    // An embedder's handler replaces the command; pre-suspend still runs first
+    if let Some(handler) = mgr.state.action_handlers.get_mut(action) {
+        let fut = handler(action);
+        if action.kind == IdleAction::Suspend {
+            mgr.trigger_pre_suspend(manual).await;
+        }
+        log_message(&format!("Running registered handler for action '{}'", action.name));
+        spawn_task_limited(&mut mgr.spawned_tasks, fut);
+        return;
+    }
+
     let requests = prepare_action(action).await;
     for req in requests {
         match req {
             ActionRequest::PreSuspend => {
-                let cmd = action.command.clone();
-                run_command_for_action(mgr, action, cmd).await;
+                mgr.trigger_pre_suspend(manual).await;
             }
             ActionRequest::RunCommand(cmd) => {
+                let cmd = match mgr.state.soft_suspend_secs {
+                    Some(secs) if action.kind == IdleAction::Suspend => {
+                        let soft = format!("rtcwake -m mem -s {}", secs);
+                        log_message(&format!("Debug soft-suspend: running '{}' instead of '{}'", soft, cmd));
+                        soft
+                    }
+                    _ => cmd,
+                };
                 run_command_for_action(mgr, action, cmd).await;
             }
             ActionRequest::Skip(_) => {}
         }
     }
+
+    if action.verify && matches!(action.kind, IdleAction::LockScreen | IdleAction::Dpms) {
+        spawn_verification(mgr, action);
+    }
 }
 
 pub async fn run_command_for_action(mgr: &mut Manager, action: &IdleActionBlock, cmd: String) {
     let is_lock = matches!(action.kind, crate::config::model::IdleAction::LockScreen);
     if is_lock {
-        match run_command_detached(&cmd).await {
-            Ok(pid) => {
-                mgr.state.lock_state.pid = Some(pid);
+        match run_command_detached_with(&cmd, &action.command_options(mgr.state.action_shell())).await {
+            Ok(child) => {
+                mgr.state.lock_state.child = Some(child);
+                log_message(&format!("Lock screen started with PID {:?}", mgr.state.lock_state.pid()));
                 mgr.state.lock_state.is_locked = true;
-                log_message(&format!("Lock screen started with PID {}", pid));
             }
-            Err(e) => log_message(&format!("Failed to run lock command '{}': {}", cmd, e)),
+            Err(e) => {
+                log_message(&format!("Failed to run lock command '{}': {}", cmd, e));
+                mgr.state.record_lock_failure(&format!("failed to start ({})", e));
+            }
         }
+    } else if action.kind == IdleAction::Brightness {
+        // Single flight: a new brightness command replaces one still running
+        mgr.state.cancel_brightness_task().await;
+        let opts = CommandOptions { kill_on_drop: true, ..action.command_options(mgr.state.action_shell()) };
+        mgr.state.brightness_task = Some(tokio::spawn(async move {
+            if let Err(e) = run_command_silent_with(&cmd, &opts).await {
+                log_message(&format!("Failed to run command '{}': {}", cmd, e));
+            }
+        }));
     } else {
+        let opts = action.command_options(mgr.state.action_shell());
         let spawned = tokio::spawn(async move {
-            if let Err(e) = run_command_silent(&cmd).await {
+            if let Err(e) = run_command_silent_with(&cmd, &opts).await {
                 log_message(&format!("Failed to run command '{}': {}", cmd, e));
             }
         });
@@ -183,7 +444,50 @@ pub async fn run_command_for_action(mgr: &mut Manager, action: &IdleActionBlock,
     }
 }
 
-pub async fn lock_still_active(state: &ManagerState) -> bool {
+/// Start an unordered (custom) action's command as a limited background task
+/// without waiting for it; used by parallel dispatch
+fn spawn_unordered_action(mgr: &mut Manager, action: &IdleActionBlock) {
+    log_message(&format!("Action dispatched concurrently: name=\"{}\"", action.name));
+
+    if !action_allowed(action) {
+        return;
+    }
+
+    if let Some(handler) = mgr.state.action_handlers.get_mut(action) {
+        let fut = handler(action);
+        mgr.state.metrics.record_fired(&action.kind);
+        mark_once_per_boot(action);
+        spawn_task_limited(&mut mgr.spawned_tasks, fut);
+        return;
+    }
+
+    if action.session_command().trim().is_empty() {
+        return;
+    }
+
+    mgr.state.metrics.record_fired(&action.kind);
+    mark_once_per_boot(action);
+
+    let cmd = action.session_command().to_string();
+    let opts = action.command_options(mgr.state.action_shell());
+    spawn_task_limited(&mut mgr.spawned_tasks, async move {
+        if let Err(e) = run_command_silent_with(&cmd, &opts).await {
+            log_message(&format!("Failed to run command '{}': {}", cmd, e));
+        }
+    });
+}
+
+/// Whether the lock screen is up: the lock command we launched is still
+/// running, or a process matching the lock command is
+pub async fn lock_still_active(state: &mut ManagerState) -> bool {
+    // `try_wait` reaps a locker that already died, so neither check mistakes
+    // its zombie for a running lock. A forking locker (`swaylock -f`) exits
+    // here once locked and is found by the command check instead.
+    if let Some(child) = state.lock_state.child.as_mut()
+        && matches!(child.try_wait(), Ok(None))
+    {
+        return true;
+    }
     if let Some(cmd) = &state.lock_state.command {
         is_process_running(cmd).await
     } else {
@@ -192,23 +496,14 @@ pub async fn lock_still_active(state: &ManagerState) -> bool {
 }
 
 pub async fn trigger_all_idle_actions(mgr: &mut Manager) {
-    use crate::config::model::IdleAction;
-
-    let block_name = if !mgr.state.ac_actions.is_empty() || !mgr.state.battery_actions.is_empty() {
-        match mgr.state.on_battery() {
-            Some(true) => "battery",
-            Some(false) => "ac",
-            None => "default",
-        }
-    } else {
-        "default"
-    };
+    let block_name = mgr.state.active_block_name();
 
     // Clone the actions so we don't borrow mgr mutably while iterating
     let actions_to_trigger: Vec<IdleActionBlock> = match block_name {
         "ac" => mgr.state.ac_actions.clone(),
         "battery" => mgr.state.battery_actions.clone(),
         "default" => mgr.state.default_actions.clone(),
+        "profile" => mgr.state.profile_actions.clone(),
         _ => unreachable!(),
     };
 
@@ -219,6 +514,22 @@ pub async fn trigger_all_idle_actions(mgr: &mut Manager) {
 
     log_message(&format!("Triggering all idle actions for '{}'", block_name));
 
+    let dispatch = mgr.state.cfg.as_ref().map(|c| c.action_dispatch).unwrap_or_default();
+
+    // Parallel: custom actions have no ordering constraints, so start them all
+    // at once; the ordered ones (lock, DPMS, brightness, suspend) run last
+    let actions_to_trigger = if dispatch == ActionDispatch::Parallel {
+        let (unordered, ordered): (Vec<_>, Vec<_>) = actions_to_trigger
+            .into_iter()
+            .partition(|a| a.kind == IdleAction::Custom);
+        for action in &unordered {
+            spawn_unordered_action(mgr, action);
+        }
+        ordered
+    } else {
+        actions_to_trigger
+    };
+
     for action in actions_to_trigger {
         // Skip lockscreen if already locked
         if matches!(action.kind, IdleAction::LockScreen) && mgr.state.lock_state.is_locked {
@@ -227,7 +538,7 @@ pub async fn trigger_all_idle_actions(mgr: &mut Manager) {
         }
 
         log_message(&format!("Triggering idle action '{}'", action.name));
-        run_action(mgr, &action).await;
+        run_action(mgr, &action, true).await;
     }
 
     // Now update `last_triggered` after all actions are done
@@ -236,6 +547,7 @@ pub async fn trigger_all_idle_actions(mgr: &mut Manager) {
         "ac" => &mut mgr.state.ac_actions,
         "battery" => &mut mgr.state.battery_actions,
         "default" => &mut mgr.state.default_actions,
+        "profile" => &mut mgr.state.profile_actions,
         _ => unreachable!(),
     };
 
@@ -243,7 +555,74 @@ pub async fn trigger_all_idle_actions(mgr: &mut Manager) {
         a.last_triggered = Some(now);
     }
 
-    mgr.state.action_index = actions_mut.len().saturating_sub(1);
+    mgr.state.action_index = actions_mut.len();
     log_message("All idle actions triggered manually");
 }
 
+/// How long a `verify = true` action gets to take effect
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// True when every connected DRM output reports DPMS off. None when sysfs has
+/// no connected outputs to look at.
+fn outputs_dpms_off() -> Option<bool> {
+    let mut seen = false;
+    for entry in fs::read_dir("/sys/class/drm").ok()?.flatten() {
+        let dir = entry.path();
+        let connected = fs::read_to_string(dir.join("status"))
+            .is_ok_and(|s| s.trim() == "connected");
+        let Ok(dpms) = fs::read_to_string(dir.join("dpms")) else { continue };
+        if !connected {
+            continue;
+        }
+        seen = true;
+        if dpms.trim() != "Off" {
+            return Some(false);
+        }
+    }
+    seen.then_some(true)
+}
+
+/// Post-check for `verify = true`: poll until the lock screen is up (lock
+/// process or logind `LockedHint`) or the outputs are off, and log loudly when
+/// the command ran but had no effect. Runs in the background, without the manager.
+fn spawn_verification(mgr: &mut Manager, action: &IdleActionBlock) {
+    let action = action.clone();
+    let spawned = tokio::spawn(async move {
+        let deadline = Instant::now() + VERIFY_TIMEOUT;
+        loop {
+            let ok = match action.kind {
+                IdleAction::LockScreen => {
+                    is_process_running(action.session_command()).await
+                        || session_locked_hint().await.unwrap_or(false)
+                }
+                IdleAction::Dpms => match outputs_dpms_off() {
+                    Some(off) => off,
+                    None => {
+                        log_debug_message(&format!(
+                            "Cannot verify action '{}': no DPMS state in /sys/class/drm",
+                            action.name
+                        ));
+                        return;
+                    }
+                },
+                _ => return,
+            };
+
+            if ok {
+                log_debug_message(&format!("Verified action '{}' took effect", action.name));
+                return;
+            }
+            if Instant::now() >= deadline {
+                log_error_message(&format!(
+                    "VERIFY FAILED: action '{}' ran but {} after {}s; check its command",
+                    action.name,
+                    if action.kind == IdleAction::LockScreen { "the screen is not locked" } else { "outputs are still on" },
+                    VERIFY_TIMEOUT.as_secs()
+                ));
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    });
+    mgr.spawned_tasks.push(spawned);
+}
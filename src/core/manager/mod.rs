@@ -3,23 +3,72 @@ pub mod helpers;
 pub mod state;
 pub mod tasks;
 
-use std::{sync::Arc, time::{Duration, Instant}};
+use std::{
+    any::Any,
+    panic::AssertUnwindSafe,
+    sync::{atomic::{AtomicU64, Ordering}, Arc, OnceLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use futures::{future::BoxFuture, FutureExt};
 use tokio::{
     sync::Mutex, 
     task::JoinHandle, 
-    time::{Instant as TokioInstant, sleep, sleep_until}
+    time::{Instant as TokioInstant, sleep, sleep_until, timeout}
 };
 
-pub use self::state::ManagerState;
+pub use self::state::{ActionHandler, InhibitReason, ManagerState};
 use crate::{
-    config::model::{IdleAction, StasisConfig}, 
+    config::model::{IdleAction, IdleActionBlock, StasisConfig}, 
     core::manager::{
-        actions::{is_process_running, run_command_detached},
-        helpers::{restore_brightness, run_action}, 
+        actions::{is_process_running, run_command_detached_with, run_command_silent_with, CommandOptions},
+        helpers::{clear_brightness_state, lock_still_active, restore_brightness, run_action, wake_idle_tasks}, 
+        tasks::spawn_task_limited,
     }, 
-    log::log_message
+    core::utils::format_duration,
+    log::{log_debug_message, log_error_message, log_message}
 };
 
+/// How long `panic` and `lock_before_suspend` wait for the lock screen before
+/// suspending anyway
+pub const LOCK_WAIT_SECS: u64 = 5;
+
+/// A lock process that exits sooner than this after launch is treated as a
+/// failed lock command rather than an unlock
+const LOCK_INSTANT_EXIT: Duration = Duration::from_millis(1500);
+
+/// Longest a resume command may run in `resume_sequential` mode before it is
+/// killed and the next one starts
+const RESUME_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest a `snooze` may push the idle countdown out from now
+const MAX_SNOOZE: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// When the first manager was created, for `ping`'s uptime
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Unix time (ms) the idle loop last finished a wakeup, 0 before the first.
+/// Atomic so `ping` can read it without the manager lock, which a stuck loop
+/// could be holding.
+static LAST_IDLE_TICK_MS: AtomicU64 = AtomicU64::new(0);
+
+/// How long this daemon has been running
+pub fn uptime() -> Duration {
+    STARTED.get().map(Instant::elapsed).unwrap_or_default()
+}
+
+/// When the idle loop last ran `check_timeouts` (or found the timers held)
+pub fn last_idle_tick() -> Option<SystemTime> {
+    match LAST_IDLE_TICK_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(UNIX_EPOCH + Duration::from_millis(ms)),
+    }
+}
+
+fn record_idle_tick() {
+    let ms = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+    LAST_IDLE_TICK_MS.store(ms, Ordering::Relaxed);
+}
+
 pub struct Manager {
     pub state: ManagerState,
     pub spawned_tasks: Vec<JoinHandle<()>>,
@@ -31,6 +80,7 @@ pub struct Manager {
 
 impl Manager {
     pub fn new(cfg: Arc<StasisConfig>) -> Self {
+        STARTED.get_or_init(Instant::now);
         Self {
             state: ManagerState::new(cfg),
             spawned_tasks: Vec::new(),
@@ -46,11 +96,16 @@ impl Manager {
             return;
         }
 
-        let instant_actions = self.state.instant_actions.clone();
+        // Only the instants of the block that applies right now
+        let block_names: Vec<&str> = self.state.active_actions().iter().map(|a| a.name.as_str()).collect();
+        let instant_actions: Vec<_> = self.state.instant_actions.iter()
+            .filter(|a| block_names.contains(&a.name.as_str()))
+            .cloned()
+            .collect();
 
-        log_message("Triggering instant actions at startup...");
+        log_message("Triggering instant actions...");
         for action in instant_actions {
-            run_action(self, &action).await;
+            run_action(self, &action, false).await;
         }
 
         self.state.instants_triggered = true;
@@ -61,6 +116,24 @@ impl Manager {
         log_message("Instant actions reset; they can trigger again");
     }
 
+    /// Re-arm and immediately re-fire the instant actions of the active block
+    /// (`reset-instants`), e.g. after changing the setup they configure
+    pub async fn rerun_instant_actions(&mut self) {
+        // A brightness captured while no timed brightness action has dimmed the
+        // screen is not a level anyone needs restored; drop it so the re-fired
+        // instant captures the current one
+        let dimmed = self.state.active_actions().iter()
+            .any(|a| a.kind == IdleAction::Brightness && !a.is_instant() && a.last_triggered.is_some());
+        if !dimmed {
+            self.state.previous_brightness = None;
+            self.state.active_flags.brightness_captured = false;
+            clear_brightness_state();
+        }
+
+        self.reset_instant_actions();
+        self.trigger_instant_actions().await;
+    }
+
     // Called when libinput service resets (on user activity)
     pub async fn reset(&mut self) {
         let cfg = match &self.state.cfg {
@@ -77,14 +150,30 @@ impl Manager {
                 log_message(&format!("Failed to restore brightness: {}", e));
             }
         }
+        // User is back: the next idle session may capture a fresh value
+        self.state.active_flags.brightness_captured = false;
+        // ...and gets another try at a lock command that kept failing
+        self.state.clear_lock_failures();
+        if self.state.cancel_suspend_countdown() {
+            log_message("Suspend cancelled: activity during the countdown");
+            self.state.log_event("suspend_cancelled", serde_json::json!({}));
+        }
         
         let now = Instant::now();
         self.state.last_activity_display = now;
-        let debounce = Duration::from_secs(cfg.debounce_seconds as u64);
-        self.state.debounce = Some(now + debounce);
+        self.state.debounce = Some(now + cfg.debounce);
+
+        // Count an idle -> active cycle if anything fired since the last activity
+        if self.state.fired_since_activity() {
+            self.state.metrics.idle_cycles += 1;
+        }
+        // Still idle behind the lock screen until it is dismissed
+        if !self.state.lock_state.is_locked {
+            self.state.idle_since = None;
+        }
 
         // Clear only actions that are before or equal to the current stage
-        for actions in [&mut self.state.default_actions, &mut self.state.ac_actions, &mut self.state.battery_actions] {
+        for actions in [&mut self.state.default_actions, &mut self.state.ac_actions, &mut self.state.battery_actions, &mut self.state.profile_actions] {
             let mut past_lock = false;
             for a in actions.iter_mut() {
                 if matches!(a.kind, crate::config::model::IdleAction::LockScreen) {
@@ -97,126 +186,190 @@ impl Manager {
                 a.last_triggered = None;
             }
         }        
-        let block_name = if !self.state.ac_actions.is_empty() || !self.state.battery_actions.is_empty() {
-            match self.state.on_battery() {
-                Some(true) => "battery",
-                Some(false) => "ac",
-                None => "default",
-            }
-        } else {
-            "default"
-        };
+        let block_name = self.state.active_block_name();
 
         // Only update current_block if it changed
         if self.state.current_block.as_deref() != Some(block_name) {
             self.state.current_block = Some(block_name.to_string());
         }
 
+        // Whether the lock is still up; nothing to check for a lock started
+        // elsewhere without a lock command
+        let lock_alive = self.state.lock_state.is_locked
+            && ((self.state.lock_state.child.is_none() && self.state.lock_state.command.is_none())
+                || lock_still_active(&mut self.state).await);
+
         // Recompute action_index for the current block
         let actions = match block_name {
             "ac" => &mut self.state.ac_actions,
             "battery" => &mut self.state.battery_actions,
             "default" => &mut self.state.default_actions,
+            "profile" => &mut self.state.profile_actions,
             _ => unreachable!(),
         };
 
-        // Skip instant actions here. handled elsewhere
-        let index = actions.iter()
-            .position(|a| a.last_triggered.is_none())
-            .unwrap_or(actions.len().saturating_sub(1));
-
-        if actions[index].is_instant() {
-            return;
-        }
+        // Start over from the first timed action that hasn't fired; instants are
+        // handled elsewhere. Past the end when everything has (an empty block, or
+        // only stages after a still-running lock were kept)
+        self.state.action_index = actions.iter()
+            .position(|a| a.last_triggered.is_none() && !a.is_instant())
+            .unwrap_or(actions.len());
 
-        if self.state.lock_state.is_locked {
-            if let Some(lock_index) = actions.iter().position(|a| matches!(a.kind, crate::config::model::IdleAction::LockScreen)) {
-                // Check if lock process is still running
-                let still_active = if let Some(cmd) = &self.state.lock_state.command {
-                    is_process_running(cmd).await
-                } else {
-                    true // Assume lock is active if no command is specified
-                };
+        if lock_alive
+            && let Some(lock_index) = actions.iter().position(|a| matches!(a.kind, crate::config::model::IdleAction::LockScreen))
+        {
+            // Always advance to one past lock when locked. With the lock
+            // last that is the end of the sequence: nothing is left to
+            // time until the unlock starts it over
+            self.state.action_index = lock_index + 1;
+            if let Some(next) = actions.get_mut(lock_index + 1) {
+                next.last_triggered = Some(now + cfg.debounce);
+            }
 
-                if still_active {
-                    // Always advance to one past lock when locked
-                    self.state.action_index = lock_index.saturating_add(1);
-                    
-                    let debounce_end = now + debounce;
-                    if self.state.action_index < actions.len() {
-                        actions[self.state.action_index].last_triggered = Some(debounce_end); 
-                    } else {
-                        // If at the end, reset last_triggered for the last action
-                        actions[lock_index].last_triggered = Some(debounce_end);
-                    }
-                    
-                    self.state.lock_state.post_advanced = true;
-                } 
-            } 
+            self.state.lock_state.post_advanced = true;
         }
         
-        self.fire_resume_queue().await;
+        self.fire_resume_queue(false).await;
         self.state.notify.notify_one();
     }
 
+    /// Activity from input or the compositor. Events within `activity_debounce_ms`
+    /// of the last full `reset()` only push the timers back, so a burst of events
+    /// doesn't re-run brightness restore and resume commands each time. Anything
+    /// that fired in between always gets a full reset.
+    pub async fn register_activity(&mut self) {
+        let now = Instant::now();
+        let window = self.state.cfg.as_ref().map(|c| c.activity_debounce_ms).unwrap_or(0);
+        let recent = self.state.last_activity_reset
+            .is_some_and(|t| now.duration_since(t) < Duration::from_millis(window));
+
+        if recent && !self.state.fired_since_activity() && self.state.previous_brightness.is_none() {
+            if self.state.cancel_suspend_countdown() {
+                log_message("Suspend cancelled: activity during the countdown");
+            }
+            let debounce = self.state.cfg.as_ref().map(|c| c.debounce).unwrap_or_default();
+            self.state.last_activity_display = now;
+            self.state.debounce = Some(now + debounce);
+            return;
+        }
+
+        self.state.last_activity_reset = Some(now);
+        self.reset().await;
+    }
+
     // Check whether we have been idle enough to elapse one of the timeouts
     pub async fn check_timeouts(&mut self) {
-        if self.state.paused || self.state.manually_paused {
+        if self.timers_held() {
+            // Restarted from scratch once the timers run again
+            if self.state.cancel_suspend_countdown() {
+                log_message("Suspend countdown cancelled: timers held");
+            }
             return;
         }
+        if self.state.paused && !self.state.inhibit_overridden {
+            self.state.inhibit_overridden = true;
+            let reasons: Vec<String> = self.state.inhibit_reasons.iter().map(|r| r.to_string()).collect();
+            log_message(&format!(
+                "Inhibited for longer than max_inhibit_seconds ({}), running idle actions anyway",
+                reasons.join(", ")
+            ));
+            self.state.log_event("inhibit_override", serde_json::json!({ "reasons": reasons }));
+        }
         
         let now = Instant::now();
         if let Some(until) = self.state.debounce {
             if now < until {
                 return;
             } else {
-                self.state.last_activity = now;
+                self.state.last_activity = self.state.activity_anchor(now);
                 self.state.debounce = None;
             }
         }
+
+        // No timed action to fire them with: deferred instants go off on their own
+        if self.deferred_instants_only() {
+            self.trigger_instant_actions().await;
+            return;
+        }
         
         // Determine which block to use
-        let block_name = if !self.state.ac_actions.is_empty() || !self.state.battery_actions.is_empty() {
-            match self.state.on_battery() {
-                Some(true) => "battery",
-                Some(false) => "ac",
-                None => "default",
-            }
-        } else {
-            "default"
-        };
+        let block_name = self.state.active_block_name();
         
         // Only update if changed
         if self.state.current_block.as_deref() != Some(block_name) {
             self.state.current_block = Some(block_name.to_string());
         }
             
+        let lock_hold_until = self.state.lock_hold_until();
+        let requirement_met = self.state.active_actions()
+            .get(self.state.action_index)
+            .is_none_or(|a| self.state.requirement_met(a));
+
         // Get reference to the right actions Vec
         let actions = match block_name {
             "ac" => &mut self.state.ac_actions,
             "battery" => &mut self.state.battery_actions,
             "default" => &mut self.state.default_actions,
+            "profile" => &mut self.state.profile_actions,
             _ => unreachable!(),
         };
         
-        if actions.is_empty() {
+        // Empty block, or every action has fired since the last activity
+        if self.state.action_index >= actions.len() {
             return;
         }
         
-        let index = self.state.action_index.min(actions.len() - 1);
+        let index = self.state.action_index;
         
         // Skip lock if already locked
         if matches!(actions[index].kind, crate::config::model::IdleAction::LockScreen) 
             && self.state.lock_state.is_locked {
+            log_debug_message(&format!(
+                "Skipping action '{}': kind {} already active",
+                actions[index].name, actions[index].kind
+            ));
             return;
         }
-        
-        // Calculate elapsed - read the data we need before calling run_action
-        let last_ref = actions[index].last_triggered.unwrap_or(self.state.last_activity);
-        let elapsed = now.duration_since(last_ref);
-        let timeout = actions[index].timeout;
-        
-        if elapsed >= Duration::from_secs(timeout as u64) {
+
+        // Just unlocked, or backing off after a failed lock command
+        if matches!(actions[index].kind, IdleAction::LockScreen)
+            && lock_hold_until.is_some_and(|until| now < until)
+        {
+            log_debug_message(&format!(
+                "Holding action '{}': within relock grace period or lock retry backoff",
+                actions[index].name
+            ));
+            return;
+        }
+
+        // Hold (without advancing) until the prerequisite has fired
+        if !requirement_met {
+            log_debug_message(&format!(
+                "Holding action '{}': requires '{}'",
+                actions[index].name,
+                actions[index].requires.as_deref().unwrap_or_default()
+            ));
+            return;
+        }
+
+        if now >= actions[index].fire_time(self.state.last_activity) {
+            // Announce an idle suspend first; activity in the meantime resets the
+            // sequence (and the countdown) like any other
+            let countdown = self.state.cfg.as_ref().map_or(0, |c| c.suspend_countdown_seconds);
+            if actions[index].kind == IdleAction::Suspend
+                && countdown > 0
+                && !self.state.inhibited_kinds.contains(&IdleAction::Suspend)
+            {
+                match &self.state.suspend_countdown {
+                    None => {
+                        self.state.start_suspend_countdown(countdown);
+                        return;
+                    }
+                    Some(c) if now < c.until => return,
+                    Some(_) => self.state.suspend_countdown = None,
+                }
+            }
+
             // Clone the action to pass to run_action (avoids borrow conflict)
             let action_clone = actions[index].clone();
             
@@ -226,63 +379,269 @@ impl Manager {
             // Advance index
             self.state.action_index += 1;
             if self.state.action_index < actions.len() {
+                // Anchor for the next action when it is relative
                 actions[self.state.action_index].last_triggered = Some(now);
                 self.state.resume_commands_fired = false;
-            } else {
-                self.state.action_index = actions.len() - 1;
             }
 
-            // Add to resume_queue, except if already queued
+            // Outside its time-of-day window: pass over it so the sequence carries on.
+            // Local time is read on every wakeup, so the window tracks the clock.
+            if let Some(window) = action_clone.window
+                && !window.contains_now()
+            {
+                log_debug_message(&format!(
+                    "Skipping action '{}': outside its active window {}",
+                    action_clone.name, window
+                ));
+                return;
+            }
+
+            // Inhibited kind (`inhibit <kind>`): pass over it so the other kinds
+            // later in the sequence still run
+            if self.state.inhibited_kinds.contains(&action_clone.kind) {
+                log_debug_message(&format!(
+                    "Skipping action '{}': kind {} is inhibited",
+                    action_clone.name, action_clone.kind
+                ));
+                return;
+            }
+
+            // The locker already covers (and may blank) the screen
+            if matches!(action_clone.kind, IdleAction::Brightness | IdleAction::Dpms)
+                && self.state.lock_state.is_locked
+                && self.state.cfg.as_ref().is_some_and(|c| c.skip_display_while_locked)
+            {
+                log_debug_message(&format!(
+                    "Skipping action '{}': screen is locked (skip_display_while_locked)",
+                    action_clone.name
+                ));
+                return;
+            }
+
+            // Add to resume_queue, except if already queued. Keyed by name so
+            // actions sharing a kind (e.g. several custom ones) each get resumed.
             if matches!(action_clone.kind, IdleAction::LockScreen) {
                 // Do NOT push lock actions to resume_queue
-            } else if action_clone.resume_command.is_some() {
+            } else if action_clone.has_resume_command()
+                && !self.state.resume_queue.iter().any(|a| a.name == action_clone.name)
+            {
                 self.state.resume_queue.push(action_clone.clone());
             }
             
+            // Instants armed but not fired at startup (trigger_instant_on_startup = false)
+            // go off together with the first idle action
+            if !self.state.instants_triggered {
+                self.trigger_instant_actions().await;
+            }
+
             // Now we can call run_action with full mutable self access
-            run_action(self, &action_clone).await;
+            run_action(self, &action_clone, false).await;
         }
     }
 
-    pub async fn fire_resume_queue(&mut self) {
+    /// Run queued resume commands. On user activity (`system_resume == false`) actions
+    /// with `also_run_on_activity = false` stay queued until the system resumes from suspend.
+    pub async fn fire_resume_queue(&mut self, system_resume: bool) {
         if self.state.resume_queue.is_empty() {
             return;
         }
 
-        log_message(&format!("Firing {} queued resume command(s)...", self.state.resume_queue.len()));
+        let (to_fire, kept): (Vec<_>, Vec<_>) = self
+            .state
+            .resume_queue
+            .drain(..)
+            .partition(|a| system_resume || a.also_run_on_activity);
+        self.state.resume_queue = kept;
 
-        for action in self.state.resume_queue.drain(..) {
-            if let Some(resume_cmd) = &action.resume_command {
-                log_message(&format!("Running resume command for action: {}", action.name));
-                if let Err(e) = run_command_detached(resume_cmd).await {
-                    log_message(&format!("Failed to run resume command '{}': {}", resume_cmd, e));
-                }
+        if to_fire.is_empty() {
+            return;
+        }
+
+        log_message(&format!("Firing {} queued resume command(s)...", to_fire.len()));
+
+        // `last_activity` still marks the start of the idle period here
+        let idle = self.state.last_activity.elapsed();
+        let shell = &self.state.action_shell();
+        // Undo in reverse firing order (the queue is in firing order), each
+        // action's own commands in the order listed
+        let commands: Vec<(String, String, CommandOptions)> = to_fire.iter().rev()
+            .flat_map(|action| action.resume_commands.iter().map(move |cmd| {
+                (action.name.clone(), cmd.clone(), action.command_options(shell.clone()).with_idle_seconds(idle))
+            }))
+            .collect();
+
+        if self.state.cfg.as_ref().is_some_and(|c| c.resume_sequential) {
+            // Each waits for the previous to exit; off the manager lock
+            spawn_task_limited(&mut self.spawned_tasks, run_resume_sequence(commands));
+            return;
+        }
+
+        for (name, cmd, opts) in commands {
+            log_message(&format!("Running resume command for action: {}", name));
+            if let Err(e) = run_command_detached_with(&cmd, &opts).await {
+                log_message(&format!("Failed to run resume command '{}': {}", cmd, e));
             }
         }
+    }
+
+    /// Put the screen back the way it was before idling (brightness, DPMS and any
+    /// other queued resume commands) without counting as activity: `last_activity`
+    /// and the sequence position are left alone, so timers keep running.
+    pub async fn undo_all(&mut self) {
+        log_message("Undoing all idle effects");
+
+        if self.state.previous_brightness.is_some()
+            && let Err(e) = restore_brightness(&mut self.state).await
+        {
+            log_error_message(&format!("Failed to restore brightness: {}", e));
+        }
+
+        self.fire_resume_queue(true).await;
+    }
 
-        self.state.resume_queue.clear();
+    /// Safety net for an unexpected failure in the action pipeline: log it and,
+    /// with `restore_on_error`, undo dimming and DPMS so a bug never leaves the
+    /// screen dark
+    pub async fn restore_after_error(&mut self, cause: &str) {
+        log_error_message(&format!("Idle action pipeline failed: {}", cause));
+        self.state.log_event("pipeline_error", serde_json::json!({ "error": cause }));
+
+        if self.state.cfg.as_ref().is_none_or(|c| c.restore_on_error) {
+            log_message("restore_on_error: restoring brightness and display");
+            self.undo_all().await;
+        }
     }
 
+    /// When the current action of the active block is due, used by the idle loop to
+    /// sleep exactly until then. Only the current action matters: later ones are
+    /// scheduled from it once it fires.
     pub fn next_action_instant(&self) -> Option<Instant> {
-        if self.state.paused || self.state.manually_paused {
+        if self.timers_held() {
             return None;
         }
 
-        let mut min_time: Option<Instant> = None;
+        // Deferred instants with nothing timed to go with: due once the debounce ends
+        if self.deferred_instants_only() {
+            return Some(self.state.debounce.unwrap_or_else(Instant::now));
+        }
+
+        // None once the whole sequence has fired; activity starts it over
+        let action = self.state.active_actions().get(self.state.action_index)?;
 
-        for actions in [&self.state.default_actions, &self.state.ac_actions, &self.state.battery_actions] {
-            for action in actions.iter() {
-                let last = action.last_triggered.unwrap_or(self.state.last_activity);
-                let next_time = last + Duration::from_secs(action.timeout as u64);
-                
-                min_time = Some(match min_time {
-                    None => next_time,
-                    Some(current_min) => current_min.min(next_time),
-                });
-            }
+        // A running lock is handled by the lock watcher, which wakes the loop on unlock
+        if action.kind == IdleAction::LockScreen && self.state.lock_state.is_locked {
+            return None;
+        }
+
+        // Unmet prerequisite: rechecked on the periodic wakeup
+        if !self.state.requirement_met(action) {
+            return None;
+        }
+
+        // During debounce, last_activity is reset when the debounce ends
+        if let Some(until) = self.state.debounce
+            && until > Instant::now()
+        {
+            return Some(self.state.activity_anchor(until) + Duration::from_secs(action.timeout));
+        }
+
+        let fire_time = action.fire_time(self.state.last_activity);
+        if action.kind == IdleAction::Suspend
+            && let Some(countdown) = &self.state.suspend_countdown
+        {
+            return Some(countdown.until);
+        }
+        if action.kind == IdleAction::LockScreen
+            && let Some(until) = self.state.lock_hold_until()
+        {
+            return Some(fire_time.max(until));
+        }
+
+        Some(fire_time)
+    }
+
+    /// Instants still armed (`trigger_instant_on_startup = false`) while the active
+    /// block has no timed action they would otherwise fire with
+    fn deferred_instants_only(&self) -> bool {
+        !self.state.instants_triggered
+            && self.state.first_timed_index() >= self.state.active_actions().len()
+    }
+
+    /// Whether the idle loop has nothing to time: an instant-only config whose
+    /// instants are done, or the compositor handling idle
+    pub fn idle_loop_quiescent(&self) -> bool {
+        (!self.state.has_timed_actions() && self.state.instants_triggered)
+            || self.state.compositor_managed
+    }
+
+    /// True once a timed action has fired and no activity has happened since
+    /// (a lock screen keeps the session idle until it is dismissed)
+    pub fn is_idle(&self) -> bool {
+        self.state.idle_since.is_some()
+    }
+
+    /// When the current idle session started, i.e. its first action fired
+    pub fn idle_since(&self) -> Option<Instant> {
+        self.state.idle_since
+    }
+
+    /// When automatic inhibition stops holding the timers (`max_inhibit_seconds`)
+    pub fn inhibit_cap_deadline(&self) -> Option<Instant> {
+        let cap = self.state.cfg.as_ref()?.max_inhibit_seconds?;
+        Some(self.state.inhibited_since? + Duration::from_secs(cap))
+    }
+
+    /// Timers are on hold: manually paused, or automatically inhibited and still
+    /// within `max_inhibit_seconds`
+    pub fn timers_held(&self) -> bool {
+        self.state.manually_paused
+            || self.state.compositor_managed
+            || (self.state.paused && self.inhibit_cap_deadline().is_none_or(|d| Instant::now() < d))
+    }
+
+    pub fn is_compositor_managed(&self) -> bool {
+        self.state.compositor_managed
+    }
+
+    /// Hand idle handling to the compositor (`true`) or take it back. While
+    /// compositor-managed the timers are held, so the idle loop goes quiescent
+    /// until it is woken again; `trigger` and the other manual commands still
+    /// run. Taking it back restarts the sequence as if the user had just been
+    /// active. Returns false when the flag was already set that way.
+    pub async fn set_compositor_managed(&mut self, managed: bool) -> bool {
+        if self.state.compositor_managed == managed {
+            return false;
+        }
+        self.state.compositor_managed = managed;
+        if managed {
+            log_message("Compositor manages idle now, timeout actions stand down");
+        } else {
+            log_message("Compositor handed idle back, restarting the idle sequence");
+            self.reset().await;
+        }
+        self.state.log_event("compositor_managed", serde_json::json!({ "managed": managed }));
+        wake_idle_tasks(&self.state);
+        true
+    }
+
+    /// How far the countdown to the current action has run, 0.0 to 1.0, for
+    /// progress UIs. None whenever `next_action_instant` is None (paused, sequence
+    /// finished, held); stays at 0.0 during debounce.
+    pub fn idle_progress(&self) -> Option<f64> {
+        let due = self.next_action_instant()?;
+        let action = self.state.active_actions().get(self.state.action_index)?;
+        let window = Duration::from_secs(action.timeout);
+        if window.is_zero() {
+            return Some(1.0);
         }
 
-        min_time
+        let now = Instant::now();
+        let start = due.checked_sub(window).unwrap_or(now);
+        if now <= start {
+            return Some(0.0);
+        }
+        let elapsed = now.duration_since(start).as_secs_f64();
+        Some((elapsed / window.as_secs_f64()).clamp(0.0, 1.0))
     }
 
     pub async fn trigger_pre_suspend(&mut self, manual: bool) {
@@ -296,63 +655,532 @@ impl Manager {
             has_pre_suspend = true;
             let cmd = cmd.clone();
             
-            if let Err(e) = run_command_detached(&cmd).await {
+            if let Err(e) = run_command_detached_with(&cmd, &self.state.hook_options()).await {
                 log_message(&format!("Pre-suspemd command failed: {}", e));
 
             }
         }
 
-        if has_pre_suspend {
-           sleep(Duration::from_millis(700)).await;
+        // Give the pre-suspend command time to settle before the system sleeps
+        let settle_ms = self.state.cfg.as_ref().map(|c| c.pre_suspend_settle_ms).unwrap_or(700);
+        if has_pre_suspend && settle_ms > 0 {
+           sleep(Duration::from_millis(settle_ms)).await;
+        }
+    }
+
+    /// Look up an action the way `trigger` does: by name, then by kind, in the
+    /// active block or the block named by an "ac." / "battery." prefix
+    fn find_action_by_name(&self, name: &str) -> Result<IdleActionBlock, String> {
+        let normalized = name.replace('_', "-").to_lowercase();
+
+        // Only a known block counts as a qualifier; other dots belong to the name
+        let (block_name, wanted) = match normalized.split_once('.') {
+            Some((block, rest)) if self.state.block_actions(block).is_some() => (block.to_string(), rest.to_string()),
+            _ => (self.state.active_block_name().to_string(), normalized.clone()),
+        };
+
+        let block = match self.state.block_actions(&block_name) {
+            Some(b) if !b.is_empty() => b,
+            _ => {
+                return Err(format!(
+                    "Action block '{}' does not exist in the current config",
+                    block_name
+                ));
+            }
+        };
+
+        // Prefer an exact name match so several actions sharing a kind (e.g. two
+        // custom actions) can each be addressed; fall back to the kind name.
+        let candidates: Vec<_> = block.iter().chain(self.state.manual_actions(&block_name)).collect();
+        let action = candidates
+            .iter()
+            .find(|a| a.base_name().replace('_', "-").to_lowercase() == wanted)
+            .or_else(|| {
+                candidates.iter().find(|a| a.kind.to_string().replace('_', "-") == wanted)
+            })
+            .map(|a| (*a).clone());
+
+        let action = match action {
+            Some(a) => a,
+            None => {
+                let mut available: Vec<String> = candidates.iter().map(|a| a.name.clone()).collect();
+                if self.state.pre_suspend_command.is_some() {
+                    available.push("pre_suspend".to_string());
+                }
+                available.sort();
+                return Err(format!(
+                    "Action '{}' not found. Available actions: {}",
+                    name,
+                    available.join(", ")
+                ));
+            }
+        };
+        Ok(action)
+    }
+
+    /// Command, options and resolved name that `test-action` runs for `name`,
+    /// or why there is nothing to run
+    pub fn test_command(&mut self, name: &str) -> Result<(String, String, CommandOptions), String> {
+        if is_pre_suspend_name(name) {
+            return match &self.state.pre_suspend_command {
+                Some(cmd) => Ok(("pre_suspend".to_string(), cmd.clone(), self.state.hook_options())),
+                None => Err("No pre_suspend_command is configured".to_string()),
+            };
+        }
+
+        let action = self.find_action_by_name(name)?;
+        if self.state.action_handlers.get_mut(&action).is_some() {
+            return Err(format!("Action '{}' is handled in-process, there is no command to test", action.name));
+        }
+        let cmd = action.session_command().trim().to_string();
+        if cmd.is_empty() {
+            return Err(format!("Action '{}' has no command", action.name));
+        }
+        Ok((action.name.clone(), cmd, action.command_options(self.state.action_shell())))
+    }
+
+    /// Run a single action by name or kind (e.g. "suspend", "lock-screen", a custom
+    /// action name), or the pre-suspend command. Unqualified names are looked up in the
+    /// active block; "ac.<name>" / "battery.<name>" address a specific power block.
+    /// On failure the error lists the available action names.
+    pub async fn trigger_action_by_name(&mut self, name: &str) -> Result<String, String> {
+        if is_pre_suspend_name(name) {
+            self.trigger_pre_suspend(true).await;
+            return Ok("pre_suspend".to_string());
+        }
+
+        let action = self.find_action_by_name(name)?;
+
+        log_message(&format!("Action triggered: '{}'", action.name));
+
+        if action.kind == IdleAction::LockScreen {
+            // Mark lock state and notify watcher
+            self.state.lock_state.is_locked = true;
+            self.state.lock_state.post_advanced = false;
+            self.state.lock_state.command = Some(action.command.clone());
+            self.state.lock_notify.notify_one();
+
+            run_action(self, &action, true).await;
+            self.advance_past_lock().await;
+
+            // Mirror reset() so the timers continue past the lock
+            if let Some(cfg) = self.state.cfg.clone() {
+                let now = Instant::now();
+                self.state.last_activity_display = now;
+                self.state.debounce = Some(now + cfg.debounce);
+
+                for actions in [&mut self.state.default_actions, &mut self.state.ac_actions, &mut self.state.battery_actions, &mut self.state.profile_actions] {
+                    for a in actions.iter_mut() {
+                        a.last_triggered = None;
+                    }
+                }
+
+                let actions = self.state.active_actions_mut();
+                let mut next_index = actions
+                    .iter()
+                    .position(|a| a.last_triggered.is_none())
+                    .unwrap_or_else(|| actions.len().saturating_sub(1));
+
+                // If a lock action exists, skip past it so the next timer continues properly
+                let mut post_advanced = false;
+                if let Some(lock_index) = actions.iter().position(|a| a.kind == IdleAction::LockScreen)
+                    && next_index <= lock_index
+                {
+                    next_index = lock_index.saturating_add(1);
+                    if next_index < actions.len() {
+                        actions[next_index].last_triggered = Some(now + cfg.debounce);
+                    }
+                    post_advanced = true;
+                }
+
+                self.state.action_index = next_index;
+                if post_advanced {
+                    self.state.lock_state.post_advanced = true;
+                }
+            }
+
+            // Wake idle loop to recalculate timers
+            self.state.notify.notify_one();
+        } else {
+            run_action(self, &action, true).await;
+        }
+
+        Ok(action.name)
+    }
+
+    /// With `lock_before_suspend`, start the lock action unless the session is
+    /// already locked. True when a locker was started: the caller then releases
+    /// the manager and waits for it (`wait_for_lock`) before suspending.
+    pub async fn start_lock_before_suspend(&mut self) -> bool {
+        if !self.state.cfg.as_ref().is_some_and(|c| c.lock_before_suspend) || self.state.lock_state.is_locked {
+            return false;
         }
+
+        let lock_action = self.state.active_actions()
+            .iter()
+            .find(|a| a.kind == IdleAction::LockScreen)
+            .or_else(|| self.state.cfg.as_ref()?.actions.iter().find(|a| a.kind == IdleAction::LockScreen))
+            .cloned();
+        let Some(lock_action) = lock_action else {
+            log_error_message("lock_before_suspend is set but no lock_screen action is configured");
+            return false;
+        };
+
+        log_message(&format!("Locking with '{}' before suspend", lock_action.name));
+        run_action(self, &lock_action, true).await;
+        // Failed to start, disabled, or locked elsewhere (lock_check_command)
+        self.state.lock_state.is_locked
     }
 
+    /// Feed a power source reading from the monitor or a power event. A change
+    /// takes effect only once the reading has held for `power_debounce_seconds`;
+    /// until then it is pending and the monitor's next poll commits it. Returns
+    /// true when the active source changed.
+    pub async fn observe_power_source(&mut self, on_battery: bool) -> bool {
+        let now = Instant::now();
+        if self.state.last_on_battery == Some(on_battery) {
+            if self.state.pending_power.take().is_some() {
+                log_message("Power source flapped back before settling, keeping the current block");
+            }
+            return false;
+        }
+
+        let settle = Duration::from_secs(self.state.cfg.as_ref().map_or(0, |c| c.power_debounce_seconds));
+        if self.state.last_on_battery.is_some() && !settle.is_zero() {
+            match self.state.pending_power {
+                Some((pending, since)) if pending == on_battery => {
+                    if now.duration_since(since) < settle {
+                        return false;
+                    }
+                }
+                _ => {
+                    log_debug_message(&format!(
+                        "Power source reads {}, waiting {}s for it to settle",
+                        if on_battery { "battery" } else { "AC" },
+                        settle.as_secs()
+                    ));
+                    self.state.pending_power = Some((on_battery, now));
+                    return false;
+                }
+            }
+        }
+
+        self.state.pending_power = None;
+        self.state.set_on_battery(on_battery);
+        self.update_power_source().await;
+        true
+    }
+
+    /// Switch to the action block matching the current power source. Call after
+    /// `set_on_battery`; restarts the sequence, restores a captured brightness
+    /// and re-arms instant actions.
     pub async fn update_power_source(&mut self) {
-        match self.state.on_battery() {
-            Some(true) => {
-                // on battery, proceed
+        let on_battery = self.state.on_battery();
+        if self.state.last_on_battery != on_battery {
+            // The first detection at startup is not a transition
+            if self.state.last_on_battery.is_some() {
+                self.state.log_event("power", serde_json::json!({
+                    "source": if on_battery == Some(true) { "battery" } else { "ac" },
+                }));
+                self.run_power_change_command(on_battery == Some(true)).await;
             }
-            Some(false) | None => {
-                return;
+            self.state.last_on_battery = on_battery;
+        }
+
+        self.switch_active_block().await;
+    }
+
+    /// Select a named profile (`set-profile`), or go back to the power-based
+    /// blocks with None. Restarts the sequence like a power source change.
+    pub async fn set_profile(&mut self, name: Option<&str>) -> Result<(), String> {
+        match name {
+            Some(name) => {
+                let actions = self.state.cfg.as_ref()
+                    .and_then(|c| c.profiles.get(name))
+                    .cloned()
+                    .ok_or_else(|| format!("No profile named '{}'", name))?;
+                self.state.profile = Some(name.to_string());
+                self.state.profile_actions = actions;
+                log_message(&format!("Profile '{}' selected", name));
+            }
+            None => {
+                self.state.profile = None;
+                self.state.profile_actions.clear();
+                log_message("Profile cleared, using power-based actions");
+            }
+        }
+
+        // Force the switch even when going from one profile to another
+        self.state.current_block = None;
+        self.switch_active_block().await;
+        Ok(())
+    }
+
+    /// Run `handler` instead of the shell command whenever the action `name`
+    /// fires (by timeout, `trigger` or a lid event). `name` is a full name such
+    /// as "ac.dim" or a base name ("dim") matching that action in every block.
+    /// The action must still be defined in the config; its command (which may be
+    /// `""`) is not run. The returned future is spawned, not awaited.
+    pub fn register_handler<F>(&mut self, name: impl Into<String>, handler: F)
+    where
+        F: FnMut(&IdleActionBlock) -> BoxFuture<'static, ()> + Send + Sync + 'static,
+    {
+        self.state.action_handlers.insert(name.into(), Box::new(handler));
+    }
+
+    /// Log when each action of the active block fires after the user goes idle,
+    /// e.g. "Schedule (on AC): brightness at 5m 0s, lock_screen at 10m 0s"
+    pub fn log_schedule(&self) {
+        let label = match self.state.active_block_name() {
+            "ac" => "on AC".to_string(),
+            "battery" => "on battery".to_string(),
+            "profile" => format!("profile {}", self.state.profile.as_deref().unwrap_or_default()),
+            _ => "desktop".to_string(),
+        };
+
+        let mut at = Duration::ZERO;
+        let steps: Vec<String> = self.state.active_actions()
+            .iter()
+            .filter(|a| !a.is_instant())
+            .map(|a| {
+                let timeout = Duration::from_secs(a.timeout);
+                at = if a.relative { at + timeout } else { timeout };
+                format!("{} at {}", a.base_name(), format_duration(at))
+            })
+            .collect();
+
+        if steps.is_empty() {
+            log_message(&format!("Schedule ({}): no timed actions", label));
+        } else {
+            log_message(&format!("Schedule ({}): {}", label, steps.join(", ")));
+        }
+    }
+
+    /// Restart the sequence on the block `active_block_name` now points at, if
+    /// it differs from the current one
+    async fn switch_active_block(&mut self) {
+        let block_name = self.state.active_block_name();
+        if self.state.current_block.as_deref() == Some(block_name) {
+            return;
+        }
+
+        log_message(&format!("Switching to '{}' action block", block_name));
+        self.state.current_block = Some(block_name.to_string());
+        self.log_schedule();
+        self.state.cancel_suspend_countdown();
+
+        for actions in [&mut self.state.default_actions, &mut self.state.ac_actions, &mut self.state.battery_actions, &mut self.state.profile_actions] {
+            for a in actions.iter_mut() {
+                a.last_triggered = None;
             }
         }
+
+        // The sequence starts over, so undo a dim from the old block rather than
+        // carry it over: the new block's brightness action then captures the
+        // undimmed level as its baseline instead of the old block's dim level
+        if self.state.previous_brightness.is_some()
+            && let Err(e) = restore_brightness(&mut self.state).await
+        {
+            log_error_message(&format!("Failed to restore brightness on block switch: {}", e));
+        }
+        self.state.active_flags.brightness_captured = false;
+
+        let now = Instant::now();
+        self.state.action_index = self.state.first_timed_index();
+        self.state.idle_since = None;
+        self.state.last_activity = self.state.activity_anchor(now);
+        self.state.active_flags.pre_suspend_triggered = false;
+
+        // Re-arm instants; fire them now unless they are still waiting for the first idle cycle
+        let instants_were_triggered = self.state.instants_triggered;
+        self.reset_instant_actions();
+        if instants_were_triggered {
+            self.trigger_instant_actions().await;
+        }
+
+        self.state.notify.notify_one();
     }
   
+    async fn run_power_change_command(&mut self, on_battery: bool) {
+        let Some(cfg) = &self.state.cfg else { return };
+        let (label, cmd) = if on_battery {
+            ("to_battery_command", cfg.on_power_change.to_battery_command.clone())
+        } else {
+            ("to_ac_command", cfg.on_power_change.to_ac_command.clone())
+        };
+
+        if let Some(cmd) = cmd {
+            log_message(&format!("Power source changed, running {}", label));
+            if let Err(e) = run_command_detached_with(&cmd, &self.state.hook_options()).await {
+                log_message(&format!("Failed to run {}: {}", label, e));
+            }
+        }
+    }
+
     pub async fn advance_past_lock(&mut self) {
         log_message("Advancing state past lock stage...");
         self.state.lock_state.post_advanced = true;
         self.state.lock_state.last_advanced = Some(Instant::now());
     }
 
-    pub async fn pause(&mut self, manual: bool) {
-        if manual {
+    pub async fn pause(&mut self, reason: InhibitReason) {
+        if reason == InhibitReason::Manual {
             self.state.manually_paused = true;
+            self.state.manual_pause_until = None;
             self.state.paused = false;
             log_message("Idle timers manually paused");
-        } else if !self.state.manually_paused {
+            self.state.log_event("pause", serde_json::json!({ "reason": reason.to_string() }));
+            return;
+        }
+
+        let reason_text = reason.to_string();
+        if !self.state.inhibit_reasons.iter().any(|r| r.same_kind(&reason)) {
+            log_message(&format!("Idle inhibited: {}", reason));
+            self.state.inhibit_reasons.push(reason);
+        }
+        if self.state.inhibited_since.is_none() {
+            self.state.inhibited_since = Some(Instant::now());
+        }
+
+        if !self.state.manually_paused && !self.state.paused {
             self.state.paused = true;
             log_message("Idle timers automatically paused");
+            self.state.log_event("pause", serde_json::json!({ "reason": reason_text }));
         }
     }
 
-    pub async fn resume(&mut self, manually: bool) {
-        if manually {
+    /// Drop `reason` (compared by kind); timers only resume once no automatic reason is left
+    pub async fn resume(&mut self, reason: InhibitReason) {
+        if reason == InhibitReason::Manual {
             if self.state.manually_paused {
                 self.state.manually_paused = false;
-                self.state.paused = false;
+                self.state.manual_pause_until = None;
+                self.state.paused = !self.state.inhibit_reasons.is_empty();
                 log_message("Idle timers manually resumed");
+                self.state.log_event("resume", serde_json::json!({ "reason": reason.to_string() }));
             }
-        } else if !self.state.manually_paused && self.state.paused {
+            return;
+        }
+
+        self.state.inhibit_reasons.retain(|r| !r.same_kind(&reason));
+        if self.state.inhibit_reasons.is_empty() {
+            self.state.inhibited_since = None;
+            self.state.inhibit_overridden = false;
+        }
+
+        if !self.state.manually_paused && self.state.paused && self.state.inhibit_reasons.is_empty() {
             self.state.paused = false;
             log_message("Idle timers automatically resumed");
+            self.state.log_event("resume", serde_json::json!({ "reason": reason.to_string() }));
+        }
+    }
+
+    /// Media playback ended: lift the media inhibit and, with
+    /// `reset_idle_on_media_stop`, count it as activity so the countdown starts over
+    pub async fn media_stopped(&mut self) {
+        self.state.media_player = None;
+        self.resume(InhibitReason::Media(None)).await;
+
+        if self.state.cfg.as_ref().is_some_and(|c| c.reset_idle_on_media_stop) {
+            log_message("Media stopped, restarting idle countdown");
+            self.reset().await;
+        }
+    }
+
+    /// Manually pause for `duration`; the idle loop resumes when it runs out
+    pub async fn pause_for(&mut self, duration: Duration) {
+        self.pause(InhibitReason::Manual).await;
+        self.state.manual_pause_until = Some(Instant::now() + duration);
+        log_message(&format!("Idle timers paused for {}", format_duration(duration)));
+        wake_idle_tasks(&self.state);
+    }
+
+    /// Push the idle countdown back by `duration` without pausing it: every
+    /// pending action moves out, and a second snooze extends the first. Capped
+    /// at `MAX_SNOOZE` from now; returns how far out the countdown now starts
+    pub fn snooze(&mut self, duration: Duration) -> Duration {
+        let now = Instant::now();
+        let until = (self.state.last_activity.max(now) + duration).min(now + MAX_SNOOZE);
+        self.state.last_activity = until;
+        self.state.snoozed_until = Some(until);
+
+        // Mid-sequence the next action counts from the previous one, not from
+        // last_activity; start that countdown at the end of the snooze too
+        let index = self.state.action_index;
+        for action in self.state.active_actions_mut().iter_mut().skip(index) {
+            if let Some(anchor) = action.last_triggered {
+                action.last_triggered = Some(anchor.max(until));
+            }
+        }
+
+        let ahead = until.duration_since(now);
+        log_message(&format!("Idle actions snoozed for {}", format_duration(ahead)));
+        self.state.log_event("snooze", serde_json::json!({ "seconds": ahead.as_secs() }));
+        wake_idle_tasks(&self.state);
+        ahead
+    }
+
+    /// Time left on a running snooze
+    pub fn snooze_remaining(&self) -> Option<Duration> {
+        self.state.snoozed_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Time left on a timed manual pause; None when not paused or paused indefinitely
+    pub fn manual_pause_remaining(&self) -> Option<Duration> {
+        if !self.state.manually_paused {
+            return None;
+        }
+        self.state.manual_pause_until.map(|until| until.saturating_duration_since(Instant::now()))
+    }
+
+    /// Resume once a timed manual pause has run out
+    pub async fn expire_timed_pause(&mut self) {
+        if self.state.manually_paused
+            && self.state.manual_pause_until.is_some_and(|until| Instant::now() >= until)
+        {
+            log_message("Timed pause expired");
+            self.resume(InhibitReason::Manual).await;
+            self.state.last_activity = Instant::now();
+        }
+    }
+
+    /// Why timers are paused right now; manual pause wins over automatic reasons
+    pub fn inhibit_reason(&self) -> Option<InhibitReason> {
+        if self.state.manually_paused {
+            return Some(InhibitReason::Manual);
+        }
+        match self.state.inhibit_reasons.last() {
+            Some(InhibitReason::Media(_)) => Some(InhibitReason::Media(self.state.media_player.clone())),
+            other => other.cloned(),
+        }
+    }
+
+    /// Stop actions of `kind` from firing while the rest of the sequence still runs
+    /// up to them. Returns false if the kind was already inhibited.
+    pub fn inhibit_kind(&mut self, kind: IdleAction) -> bool {
+        let added = self.state.inhibited_kinds.insert(kind.clone());
+        if added {
+            log_message(&format!("Inhibiting {} actions", kind));
+        }
+        added
+    }
+
+    pub fn uninhibit_kind(&mut self, kind: &IdleAction) -> bool {
+        let removed = self.state.inhibited_kinds.remove(kind);
+        if removed {
+            log_message(&format!("No longer inhibiting {} actions", kind));
+            wake_idle_tasks(&self.state);
         }
+        removed
     }
 
     pub async fn toggle_state(&mut self, inhibit: bool) {
         if inhibit {
-            self.pause(true).await;
+            self.pause(InhibitReason::Manual).await;
         } else {
-            self.resume(true).await;
+            self.resume(InhibitReason::Manual).await;
         }
     }
 
@@ -376,6 +1204,88 @@ impl Manager {
         for handle in self.spawned_tasks.drain(..) {
             handle.abort();
         }
+        self.state.cancel_brightness_task().await;
+    }
+}
+
+/// Lock immediately, wait for the locker to come up, then suspend. Bypasses
+/// timeouts, debounce and any pause (including a manual one). The manager is
+/// unlocked while waiting, so the lock watcher and IPC keep running.
+pub async fn panic(manager: &Arc<Mutex<Manager>>) -> Result<(), String> {
+    let (lock_action, suspend_action) = {
+        let mut mgr = manager.lock().await;
+        let actions = mgr.state.active_actions();
+        let lock_action = actions.iter().find(|a| a.kind == IdleAction::LockScreen).cloned();
+        let suspend_action = actions
+            .iter()
+            .find(|a| a.kind == IdleAction::Suspend)
+            .cloned()
+            .ok_or_else(|| "No suspend action configured".to_string())?;
+
+        log_message("Panic requested: locking and suspending now");
+        match &lock_action {
+            Some(lock_action) if !mgr.state.lock_state.is_locked => run_action(&mut mgr, lock_action, true).await,
+            Some(_) => {}
+            None => log_message("No lock action configured, suspending without locking"),
+        }
+        (lock_action, suspend_action)
+    };
+
+    if lock_action.is_some() && !wait_for_lock(manager, Duration::from_secs(LOCK_WAIT_SECS)).await {
+        log_error_message("Lock screen did not come up in time, suspending anyway");
+    }
+
+    let mut mgr = manager.lock().await;
+    run_action(&mut mgr, &suspend_action, true).await;
+    Ok(())
+}
+
+/// Poll until the lock process is alive, or give up after `timeout`. The
+/// manager is locked only for each check, never across the sleep.
+pub async fn wait_for_lock(manager: &Arc<Mutex<Manager>>, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if lock_still_active(&mut manager.lock().await.state).await {
+            return true;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
+
+/// Run a suspend held back by `lock_before_suspend` once the locker is up (or
+/// has had `LOCK_WAIT_SECS`). For callers without a lock watcher, which
+/// otherwise does this.
+pub async fn finish_suspend_after_lock(manager: &Arc<Mutex<Manager>>) {
+    if manager.lock().await.state.suspend_after_lock.is_none() {
+        return;
+    }
+    if !wait_for_lock(manager, Duration::from_secs(LOCK_WAIT_SECS)).await {
+        log_error_message("Lock screen did not come up in time, suspending anyway");
+    }
+
+    let mut mgr = manager.lock().await;
+    if let Some((action, manual)) = mgr.state.suspend_after_lock.clone() {
+        run_action(&mut mgr, &action, manual).await;
+        mgr.state.suspend_after_lock = None;
+    }
+}
+
+/// Run resume commands one at a time, in order. A command still running after
+/// `RESUME_COMMAND_TIMEOUT` is killed so the rest of the sequence goes on.
+async fn run_resume_sequence(commands: Vec<(String, String, CommandOptions)>) {
+    for (name, cmd, opts) in commands {
+        log_message(&format!("Running resume command for action: {}", name));
+        let opts = CommandOptions { kill_on_drop: true, ..opts };
+        match timeout(RESUME_COMMAND_TIMEOUT, run_command_silent_with(&cmd, &opts)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log_message(&format!("Failed to run resume command '{}': {}", cmd, e)),
+            Err(_) => log_message(&format!(
+                "Resume command '{}' still running after {}s, killed it",
+                cmd,
+                RESUME_COMMAND_TIMEOUT.as_secs()
+            )),
+        }
     }
 }
 
@@ -383,15 +1293,29 @@ pub fn spawn_idle_task(manager: Arc<Mutex<Manager>>) -> JoinHandle<()> {
     tokio::spawn(async move {
         loop {
             // Grab both the next timeout and the notify handles
-            let (next_instant, notify, shutdown) = {
+            let (next_instant, pause_until, quiescent, notify, shutdown) = {
                 let mgr = manager.lock().await;
                 (
                     mgr.next_action_instant(),
+                    // Wake in time to end a timed pause or to override a long inhibition
+                    mgr.state.manual_pause_until
+                        .filter(|_| mgr.state.manually_paused)
+                        .or_else(|| mgr.inhibit_cap_deadline().filter(|_| mgr.state.paused)),
+                    mgr.idle_loop_quiescent(),
                     mgr.state.notify.clone(),
                     mgr.state.shutdown_flag.clone(),
                 )
             };
 
+            // Nothing to time: skip the periodic wakeups and wait for an event
+            // (reload, power change, compositor-managed off...)
+            if quiescent && pause_until.is_none() {
+                tokio::select! {
+                    _ = notify.notified() => continue,
+                    _ = shutdown.notified() => break,
+                }
+            }
+
             // Compute how long we should sleep           
             let sleep_deadline = match next_instant {
                 Some(instant) => {
@@ -407,6 +1331,8 @@ pub fn spawn_idle_task(manager: Arc<Mutex<Manager>>) -> JoinHandle<()> {
                 }
                 None => Instant::now() + Duration::from_secs(60),
             };
+            // Wake in time to end a timed pause
+            let sleep_deadline = pause_until.map_or(sleep_deadline, |until| sleep_deadline.min(until));
 
 
             tokio::select! {
@@ -422,15 +1348,36 @@ pub fn spawn_idle_task(manager: Arc<Mutex<Manager>>) -> JoinHandle<()> {
 
             // Now check timeouts only once after wake
             let mut mgr = manager.lock().await;
-            if !mgr.state.paused && !mgr.state.manually_paused {
-                mgr.check_timeouts().await;
+            mgr.expire_timed_pause().await;
+            if !mgr.timers_held()
+                && let Err(panic) = AssertUnwindSafe(mgr.check_timeouts()).catch_unwind().await
+            {
+                mgr.restore_after_error(&panic_message(panic.as_ref())).await;
             }
+            drop(mgr);
+            record_idle_tick();
         }
 
         log_message("Idle loop shutting down...");
     })
 }
 
+/// Text of a caught panic, for the log
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic".to_string())
+}
+
+/// Watch the lock screen until it ends, then fire the unlock hooks and restart
+/// the sequence.
+///
+/// Invariant: the manager mutex is never held across `is_process_running`, a
+/// `sleep`/`select!` or a hook command; state is snapshotted or updated in a
+/// short critical section and the guard dropped before awaiting, so the idle
+/// loop's `check_timeouts` is never starved by the lock poll. The only
+/// exceptions are `run_action` for `lock_then_suspend_after` and for a suspend
+/// held back by `lock_before_suspend`, which need the manager itself.
 pub async fn spawn_lock_watcher(manager: Arc<Mutex<Manager>>) -> JoinHandle<()> {
     tokio::spawn(async move {
         loop {
@@ -457,18 +1404,49 @@ pub async fn spawn_lock_watcher(manager: Arc<Mutex<Manager>>) -> JoinHandle<()>
                 }
             }
 
+            // Global on-lock hook, however the lock came about
+            let (on_lock, opts) = {
+                let mgr = manager.lock().await;
+                (mgr.state.cfg.as_ref().and_then(|c| c.on_lock_command.clone()), mgr.state.hook_options())
+            };
+            if let Some(cmd) = on_lock {
+                log_message("Firing on_lock_command");
+                if let Err(e) = run_command_detached_with(&cmd, &opts).await {
+                    log_message(&format!("Failed to run on_lock_command: {}", e));
+                }
+            }
+
             log_message("Lock detected — entering lock watcher");
+            let lock_started = Instant::now();
+            let mut suspended_while_locked = false;
+            // Only a lock we launched ourselves can have failed to start
+            let launched = manager.lock().await.state.lock_state.child.is_some();
+            let mut exit_status = None;
 
             // Lock is active — monitor it until it ends or shutdown
             loop {
-                // Snapshot relevant info
-                let (maybe_cmd, was_locked, shutdown, lock_notify) = {
-                    let mgr = manager.lock().await;
+                // Snapshot relevant info; `try_wait` never blocks
+                let (maybe_cmd, was_locked, shutdown, lock_notify, child_exit) = {
+                    let mut mgr = manager.lock().await;
+                    let child_exit = match mgr.state.lock_state.child.as_mut().map(|c| c.try_wait()) {
+                        Some(Ok(Some(status))) => {
+                            mgr.state.lock_state.child = None;
+                            Some(Some(status))
+                        }
+                        Some(Ok(None)) => Some(None),
+                        Some(Err(e)) => {
+                            log_error_message(&format!("Lost track of the lock process: {}", e));
+                            mgr.state.lock_state.child = None;
+                            None
+                        }
+                        None => None,
+                    };
                     (
                         mgr.state.lock_state.command.clone(),
                         mgr.state.lock_state.is_locked,
                         mgr.state.shutdown_flag.clone(),
                         mgr.state.lock_notify.clone(),
+                        child_exit,
                     )
                 };
 
@@ -476,39 +1454,143 @@ pub async fn spawn_lock_watcher(manager: Arc<Mutex<Manager>>) -> JoinHandle<()>
                     break;
                 }
 
-                // Check if process is still running (if we have a command)
-                let still_active = if let Some(cmd) = maybe_cmd {
-                    is_process_running(&cmd).await
-                } else {
-                    sleep(Duration::from_millis(500)).await;
-                    true
+                // Our own lock process is checked exactly; one started elsewhere,
+                // or one that forked into the background, by name
+                let still_active = match child_exit {
+                    Some(None) => true,
+                    Some(Some(status)) => {
+                        log_message(&format!("Lock command exited: {}", status));
+                        exit_status = Some(status);
+                        // A locker that daemonizes (`swaylock -f`) exits at once
+                        // while its forked process keeps the session locked
+                        match &maybe_cmd {
+                            Some(cmd) if status.success() => is_process_running(cmd).await,
+                            _ => false,
+                        }
+                    }
+                    None => match maybe_cmd {
+                        Some(cmd) => is_process_running(&cmd).await,
+                        None => {
+                            sleep(Duration::from_millis(500)).await;
+                            true
+                        }
+                    },
                 };
 
-                if !still_active {
+                // A suspend held back by `lock_before_suspend` goes ahead now that
+                // the locker is up, or anyway when it failed to come up
+                {
                     let mut mgr = manager.lock().await;
+                    if let Some((action, manual)) = mgr.state.suspend_after_lock.clone() {
+                        if !still_active {
+                            log_error_message("Lock screen did not come up, suspending anyway");
+                        }
+                        run_action(&mut mgr, &action, manual).await;
+                        mgr.state.suspend_after_lock = None;
+                    }
+                }
 
-                    if let Some(lock_action) = mgr.state.default_actions.iter()
-                        .chain(mgr.state.ac_actions.iter())
-                        .chain(mgr.state.battery_actions.iter())
-                        .find(|a| matches!(a.kind, crate::config::model::IdleAction::LockScreen))
-                    {
-                        if let Some(resume_cmd) = &lock_action.resume_command {
-                            log_message("Firing lockscreen resume command");
-                            if let Err(e) = run_command_detached(resume_cmd).await {
-                                log_message(&format!("Failed to run lock resume command: {}", e));
+                if !still_active {
+                    // Update state under the lock, run the hooks after releasing it
+                    let (resume_cmd, on_unlock, idle, resume_sequential) = {
+                        let mut mgr = manager.lock().await;
+
+                        let exited_immediately = launched && lock_started.elapsed() < LOCK_INSTANT_EXIT;
+
+                        if exited_immediately {
+                            // Don't restart the sequence: it would only reach the broken lock again
+                            let cause = match exit_status {
+                                Some(status) => format!("exited immediately ({})", status),
+                                None => "exited immediately".to_string(),
+                            };
+                            mgr.state.record_lock_failure(&cause);
+                            break;
+                        }
+                        mgr.state.lock_state.failures = 0;
+
+                        let resume_cmd = mgr.state.active_actions().iter()
+                            .chain(mgr.state.default_actions.iter())
+                            .chain(mgr.state.ac_actions.iter())
+                            .chain(mgr.state.battery_actions.iter())
+                            .find(|a| matches!(a.kind, crate::config::model::IdleAction::LockScreen))
+                            .filter(|a| a.has_resume_command())
+                            .map(|a| (a.name.clone(), a.resume_commands.clone(), a.command_options(mgr.state.action_shell())));
+                        let on_unlock = mgr.state.cfg.as_ref()
+                            .and_then(|c| c.on_unlock_command.clone())
+                            .map(|cmd| (cmd, mgr.state.hook_options()));
+                        let resume_sequential = mgr.state.cfg.as_ref().is_some_and(|c| c.resume_sequential);
+                        // Activity at the lock screen moves last_activity; the idle
+                        // session itself started when its first action fired
+                        let idle = mgr.state.idle_since.unwrap_or(mgr.state.last_activity).elapsed();
+
+                        mgr.state.lock_state.child = None;
+                        mgr.state.lock_state.post_advanced = false;
+                        mgr.state.lock_state.last_unlocked = Some(Instant::now());
+                        mgr.state.action_index = mgr.state.first_timed_index();
+                        mgr.state.idle_since = None;
+                        mgr.state.lock_state.is_locked = false;
+                        mgr.state.log_event("unlock", serde_json::json!({
+                            "exit_code": exit_status.and_then(|s| s.code()),
+                        }));
+                        mgr.state.notify.notify_one();
+                        (resume_cmd, on_unlock, idle, resume_sequential)
+                    };
+
+                    if let Some((name, resume_cmds, opts)) = resume_cmd {
+                        log_message("Firing lockscreen resume command");
+                        let opts = opts.with_idle_seconds(idle);
+                        if resume_sequential {
+                            // Off the watcher loop, so a slow command can't hold up unlock handling
+                            let commands = resume_cmds.into_iter()
+                                .map(|cmd| (name.clone(), cmd, opts.clone()))
+                                .collect();
+                            spawn_task_limited(&mut manager.lock().await.spawned_tasks, run_resume_sequence(commands));
+                        } else {
+                            for cmd in resume_cmds {
+                                if let Err(e) = run_command_detached_with(&cmd, &opts).await {
+                                    log_message(&format!("Failed to run lock resume command: {}", e));
+                                }
                             }
                         }
                     }
 
-                    mgr.state.lock_state.pid = None;
-                    mgr.state.lock_state.post_advanced = false;
-                    mgr.state.action_index = 0;
-                    mgr.state.lock_state.is_locked = false;
-                    mgr.state.notify.notify_one();
+                    if let Some((cmd, opts)) = on_unlock {
+                        log_message("Firing on_unlock_command");
+                        let opts = opts.with_idle_seconds(idle);
+                        if let Err(e) = run_command_detached_with(&cmd, &opts).await {
+                            log_message(&format!("Failed to run on_unlock_command: {}", e));
+                        }
+                    }
+
                     log_message("Lockscreen ended — exiting lock watcher");
                     break;
                 }
 
+                // Locked for too long: suspend on top of the lock (once per lock)
+                if !suspended_while_locked {
+                    let mut mgr = manager.lock().await;
+                    if let Some(after) = mgr.state.cfg.as_ref().and_then(|c| c.lock_then_suspend_after)
+                        && lock_started.elapsed() >= after
+                    {
+                        suspended_while_locked = true;
+                        let suspend_action = mgr.state.active_actions().iter()
+                            .find(|a| a.kind == IdleAction::Suspend)
+                            .cloned();
+                        match suspend_action {
+                            Some(action) => {
+                                log_message(&format!(
+                                    "Locked for {}, triggering suspend action '{}'",
+                                    format_duration(after), action.name
+                                ));
+                                run_action(&mut mgr, &action, false).await;
+                            }
+                            None => log_error_message(
+                                "lock_then_suspend_after is set but no suspend action is configured"
+                            ),
+                        }
+                    }
+                }
+
                 // Wait a bit or for external change / shutdown
                 tokio::select! {
                     _ = lock_notify.notified() => {},
@@ -523,4 +1605,177 @@ pub async fn spawn_lock_watcher(manager: Arc<Mutex<Manager>>) -> JoinHandle<()>
     })
 }
 
+/// "pre_suspend" and its spellings, which name the pre-suspend command rather than an action
+fn is_pre_suspend_name(name: &str) -> bool {
+    matches!(name.replace('_', "-").to_lowercase().as_str(), "pre-suspend" | "presuspend")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parser::load_config_from_str;
+
+    /// Config with the given action blocks, repeated under on_ac/on_battery so
+    /// the same sequence applies whether the host looks like a laptop or not
+    fn config(actions: &str) -> StasisConfig {
+        let nested: String = actions.lines().map(|l| format!("  {}\n", l)).collect();
+        let config = format!("stasis:\n{}  on_ac:\n{}  end\n  on_battery:\n{}  end\nend\n", actions, nested, nested);
+        load_config_from_str(&config).expect("test config parses")
+    }
+
+    fn manager(actions: &str) -> Manager {
+        let mut mgr = Manager::new(Arc::new(config(actions)));
+        mgr.state.debounce = None;
+        mgr
+    }
+
+    fn idle_for(mgr: &mut Manager, secs: u64) {
+        mgr.state.last_activity = Instant::now() - Duration::from_secs(secs);
+    }
+
+    #[tokio::test]
+    async fn snooze_mid_sequence_delays_the_next_action() {
+        let mut mgr = manager(
+            "  first:\n    timeout 10\n    command \"true\"\n  end\n  \
+             second:\n    timeout 20\n    command \"true\"\n  end\n",
+        );
+        idle_for(&mut mgr, 11);
+        mgr.check_timeouts().await;
+        assert_eq!(mgr.state.action_index, 1);
+
+        // `second` counts from `first` and is nearly due
+        mgr.state.active_actions_mut()[1].last_triggered = Some(Instant::now() - Duration::from_secs(19));
+        mgr.snooze(Duration::from_secs(60));
+
+        mgr.check_timeouts().await;
+        assert_eq!(mgr.state.action_index, 1);
+        let next = mgr.next_action_instant().expect("second is pending");
+        assert!(next >= Instant::now() + Duration::from_secs(79));
+    }
+
+    #[tokio::test]
+    async fn two_custom_actions_both_fire() {
+        let mut mgr = manager(
+            "  first:\n    timeout 60\n    relative false\n    command \"true\"\n    resume_command \"true\"\n  end\n  \
+             second:\n    timeout 120\n    relative false\n    command \"true\"\n    resume_command \"true\"\n  end\n",
+        );
+        idle_for(&mut mgr, 61);
+        mgr.check_timeouts().await;
+        idle_for(&mut mgr, 121);
+        mgr.check_timeouts().await;
+
+        assert_eq!(mgr.state.action_index, 2);
+        assert!(mgr.state.active_actions().iter().all(|a| a.last_triggered.is_some()));
+        assert_eq!(mgr.state.resume_queue.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn instant_only_config_goes_quiescent() {
+        let mut mgr = manager("  dim:\n    timeout 0\n    command \"true\"\n  end\n");
+        assert!(!mgr.idle_loop_quiescent());
+
+        mgr.trigger_instant_actions().await;
+        assert!(mgr.idle_loop_quiescent());
+        assert!(mgr.next_action_instant().is_none());
+    }
 
+    #[tokio::test]
+    async fn deferred_instants_fire_without_timed_actions() {
+        let mut mgr = manager("  dim:\n    timeout 0\n    command \"true\"\n  end\n");
+        assert!(!mgr.idle_loop_quiescent());
+        assert!(mgr.next_action_instant().is_some_and(|due| due <= Instant::now()));
+
+        mgr.check_timeouts().await;
+        assert!(mgr.state.instants_triggered);
+        assert!(mgr.idle_loop_quiescent());
+    }
+
+    #[tokio::test]
+    async fn crashed_locker_is_not_active() {
+        let mut mgr = manager("  dim:\n    timeout 10\n    command \"true\"\n  end\n");
+        mgr.state.lock_state.command = None;
+
+        mgr.state.lock_state.child = Some(tokio::process::Command::new("sleep").arg("5").kill_on_drop(true).spawn().unwrap());
+        assert!(lock_still_active(&mut mgr.state).await);
+
+        // Exited but not yet reaped: a zombie still has its /proc entry
+        let child = tokio::process::Command::new("false").spawn().unwrap();
+        let stat = format!("/proc/{}/stat", child.id().unwrap());
+        let exited = async {
+            while !std::fs::read_to_string(&stat).is_ok_and(|s| s.contains(") Z ")) {
+                tokio::task::yield_now().await;
+            }
+        };
+        timeout(Duration::from_secs(5), exited).await.expect("child exited");
+        mgr.state.lock_state.child = Some(child);
+        assert!(!lock_still_active(&mut mgr.state).await);
+    }
+
+    #[tokio::test]
+    async fn reload_keeping_timers_re_enables_a_failed_lock() {
+        let actions = "  lock_screen:\n    timeout 10\n    command \"true\"\n  end\n";
+        let mut mgr = manager(actions);
+        mgr.state.lock_state.failures = 3;
+        mgr.state.lock_state.disabled = true;
+        mgr.state.lock_state.retry_after = Some(Instant::now() + Duration::from_secs(3600));
+
+        mgr.state.update_from_config(&config(actions)).await;
+        assert!(!mgr.state.lock_state.disabled);
+        assert_eq!(mgr.state.lock_state.failures, 0);
+
+        // No longer held back: the lock is due and fires
+        idle_for(&mut mgr, 11);
+        assert!(mgr.next_action_instant().is_some_and(|due| due <= Instant::now()));
+        mgr.check_timeouts().await;
+        assert_eq!(mgr.state.action_index, 1);
+    }
+
+    #[tokio::test]
+    async fn reload_to_a_shorter_timeout_is_honored() {
+        let mut mgr = manager("  dim:\n    timeout 300\n    command \"true\"\n  end\n");
+        idle_for(&mut mgr, 10);
+        mgr.check_timeouts().await;
+        assert_eq!(mgr.state.action_index, 0);
+
+        mgr.state.update_from_config(&config("  dim:\n    timeout 5\n    command \"true\"\n  end\n")).await;
+        // The idle loop is woken to recompute its deadline
+        assert!(mgr.state.notify.notified().now_or_never().is_some());
+        assert!(mgr.next_action_instant().is_some_and(|due| due <= Instant::now()));
+
+        mgr.check_timeouts().await;
+        assert_eq!(mgr.state.action_index, 1);
+    }
+
+    #[tokio::test]
+    async fn overlapping_fades_do_not_interleave() {
+        let out = std::env::temp_dir().join(format!("stasis-fade-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&out);
+        let fade = |mark: &str| format!(
+            "for i in 1 2 3 4 5 6 7 8; do echo {} >> '{}'; sleep 0.05; done",
+            mark,
+            out.display()
+        );
+
+        let mut mgr = manager("  brightness:\n    timeout 10\n    command \"true\"\n  end\n");
+        let action = mgr.state.active_actions()[0].clone();
+        helpers::run_command_for_action(&mut mgr, &action, fade("old")).await;
+        // Supersede the old fade only once it is actually writing
+        let old_started = async {
+            while !std::fs::read_to_string(&out).is_ok_and(|w| w.contains("old")) {
+                tokio::task::yield_now().await;
+            }
+        };
+        timeout(Duration::from_secs(5), old_started).await.expect("old fade started");
+        helpers::run_command_for_action(&mut mgr, &action, fade("new")).await;
+        // The new fade runs as long as the old one would have had left, so an old
+        // fade that was not stopped writes in between
+        mgr.state.brightness_task.take().expect("new fade running").await.unwrap();
+
+        let writes = std::fs::read_to_string(&out).unwrap();
+        let _ = std::fs::remove_file(&out);
+        let lines: Vec<&str> = writes.lines().collect();
+        let first_new = lines.iter().position(|l| *l == "new").expect("new fade wrote");
+        assert!(first_new > 0, "old fade never started: {:?}", lines);
+        assert!(lines[first_new..].iter().all(|l| *l == "new"), "writes interleaved: {:?}", lines);
+    }
+}
@@ -1,11 +1,29 @@
-use std::{sync::Arc, time::{Duration, Instant}};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{atomic::{AtomicU32, Ordering}, Arc},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
 
-use tokio::sync::Notify;
+use tokio::{process::Child, sync::Notify, task::JoinHandle};
 
 use crate::{
-    config::model::{IdleAction, IdleActionBlock, StasisConfig}, log::log_message
+    config::model::{ActionShell, IdleAction, IdleActionBlock, StasisConfig}, 
+    core::{
+        manager::{actions::CommandOptions, helpers::{set_brightness_backend, BrightnessMethod}},
+        services::dbus::{close_notification, send_notification},
+        utils::is_laptop,
+    },
+    log::{log_debug_message, log_error_message, log_event, log_message}
 };
 
+/// Failed lock launches in a row before the lock action is disabled
+const LOCK_MAX_FAILURES: u32 = 4;
+
+/// Backoff after the first failed lock launch, doubled on each further failure
+const LOCK_RETRY_BASE: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 pub struct ManagerState {
     pub ac_actions: Vec<IdleActionBlock>,
@@ -19,24 +37,75 @@ pub struct ManagerState {
     pub current_block: Option<String>,
     pub debounce: Option<Instant>,
     pub default_actions: Vec<IdleActionBlock>,
+    pub inhibit_reasons: Vec<InhibitReason>,
+    /// When the current automatic inhibition began, for `max_inhibit_seconds`
+    pub inhibited_since: Option<Instant>,
+    /// `max_inhibit_seconds` ran out and the inhibition is being ignored
+    pub inhibit_overridden: bool,
+    /// Action kinds held back while idle (`inhibit <kind>`), leaving the rest running
+    pub inhibited_kinds: HashSet<IdleAction>,
     pub instant_actions: Vec<IdleActionBlock>,
     pub instants_triggered: bool,
     pub last_activity: Instant,
     pub last_activity_display: Instant,
+    /// Last full `reset()` from activity, for `activity_debounce_ms`
+    pub last_activity_reset: Option<Instant>,
+    /// When the first timed action of this idle session fired; cleared by activity
+    pub idle_since: Option<Instant>,
+    pub last_on_battery: Option<bool>,
+    /// Power reading that differs from `last_on_battery` and when it was first
+    /// seen, held until it has lasted `power_debounce_seconds`
+    pub pending_power: Option<(bool, Instant)>,
     pub lock_state: LockState,
     pub lock_notify: Arc<Notify>,
     pub manually_paused: bool,
+    /// Identity of the player currently inhibiting idle, set by the media monitor
+    pub media_player: Option<String>,
+    /// When a timed manual pause (`pause <duration>`) ends; None while indefinite
+    pub manual_pause_until: Option<Instant>,
+    /// Activity can't move the idle countdown's start before this (`snooze`)
+    pub snoozed_until: Option<Instant>,
+    /// Suspend held back by `lock_before_suspend` until the locker is up, and
+    /// whether it was triggered manually
+    pub suspend_after_lock: Option<(IdleActionBlock, bool)>,
+    pub metrics: ActionMetrics,
     pub notify: Arc<Notify>,
     pub paused: bool,
     pub previous_brightness: Option<u8>,
+    /// Brightness action command still running (e.g. a fade); only one may write
+    /// the backlight at a time
+    pub brightness_task: Option<JoinHandle<()>>,
+    /// How brightness is captured and restored (`set_brightness_backend`)
+    pub brightness_method: Option<BrightnessMethod>,
+    /// What `brightness_backend "auto"` found, probed once
+    pub brightness_probe: Option<BrightnessMethod>,
     pub pre_suspend_command: Option<String>,
+    /// Profile selected with `set-profile`; overrides the power-based block
+    pub profile: Option<String>,
+    /// Actions of the selected profile (the "profile" block)
+    pub profile_actions: Vec<IdleActionBlock>,
     pub resume_queue: Vec<IdleActionBlock>,
     pub resume_commands_fired: bool,
     pub shutdown_flag: Arc<Notify>,
+    /// Debug only (`--debug-soft-suspend`): suspend actions run a short
+    /// `rtcwake` sleep of this many seconds instead of their command
+    pub soft_suspend_secs: Option<u64>,
+    /// In-process handlers registered by an embedder, run instead of commands
+    pub action_handlers: ActionHandlers,
     pub start_time: Instant,
+    /// Running `suspend_countdown_seconds` countdown before an idle suspend
+    pub suspend_countdown: Option<SuspendCountdown>,
     pub suspend_occured: bool,
 }
 
+/// Pending idle suspend, announced with a notification
+#[derive(Debug)]
+pub struct SuspendCountdown {
+    pub until: Instant,
+    /// Id of the countdown notification once the daemon has answered, 0 before
+    pub notification: Arc<AtomicU32>,
+}
+
 impl Default for ManagerState {
     fn default() -> Self {
         let now = Instant::now();
@@ -53,21 +122,42 @@ impl Default for ManagerState {
             current_block: None,
             debounce: None,
             default_actions: Vec::new(),
+            inhibit_reasons: Vec::new(),
+            inhibited_since: None,
+            inhibit_overridden: false,
+            inhibited_kinds: HashSet::new(),
             instant_actions: Vec::new(),
             instants_triggered: false,
             last_activity: now, 
             last_activity_display: now,
+            last_activity_reset: None,
+            idle_since: None,
+            last_on_battery: None,
+            pending_power: None,
             lock_state: LockState::default(),
             manually_paused: false,
+            media_player: None,
+            manual_pause_until: None,
+            snoozed_until: None,
+            suspend_after_lock: None,
+            metrics: ActionMetrics::default(),
             notify: Arc::new(Notify::new()),
             lock_notify: Arc::new(Notify::new()),
             paused: false,
             previous_brightness: None,
+            brightness_task: None,
+            brightness_method: None,
+            brightness_probe: None,
             pre_suspend_command: None,
+            profile: None,
+            profile_actions: Vec::new(),
             resume_queue: Vec::new(),
             resume_commands_fired: false,
             shutdown_flag: Arc::new(Notify::new()),
+            soft_suspend_secs: None,
+            action_handlers: ActionHandlers::default(),
             start_time: now,
+            suspend_countdown: None,
             suspend_occured: false,
         }
     }
@@ -98,45 +188,75 @@ impl ManagerState {
 
 
         let now = Instant::now();
-        let debounce = Some(now + Duration::from_secs(cfg.debounce_seconds as u64));
+        let debounce = Some(now + cfg.debounce);
 
         let instant_actions: Vec<_> = default_actions
             .iter()
             .chain(&ac_actions)
             .chain(&battery_actions)
+            .chain(cfg.profiles.values().flatten())
             .filter(|a| a.is_instant())
             .cloned()
             .collect();
 
-        let state = Self {
+        // Must agree with the parser, which only reads on_ac/on_battery on laptops
+        let chassis = if is_laptop() {
+            ChassisType::Laptop(LaptopState { on_battery: false })
+        } else {
+            ChassisType::Desktop(DesktopState)
+        };
+
+        let mut state = Self {
             ac_actions,
             action_index: 0,
             active_flags: ActiveFlags::default(),
             app_inhibit_debounce: None,
             battery_actions,
             cfg: Some(cfg.clone()),
-            chassis: ChassisType::Desktop(DesktopState),
+            chassis,
             compositor_managed: false,
             current_block: None,
             debounce,
             default_actions,
+            inhibit_reasons: Vec::new(),
+            inhibited_since: None,
+            inhibit_overridden: false,
+            inhibited_kinds: HashSet::new(),
             instant_actions,
             instants_triggered: false,
             last_activity: now,
             last_activity_display: now,
+            last_activity_reset: None,
+            idle_since: None,
+            last_on_battery: None,
+            pending_power: None,
             lock_state: LockState::from_config(&cfg),
             manually_paused: false,
+            media_player: None,
+            manual_pause_until: None,
+            snoozed_until: None,
+            suspend_after_lock: None,
+            metrics: ActionMetrics::default(),
             notify: Arc::new(Notify::new()),
             lock_notify: Arc::new(Notify::new()),
             paused: false,
             previous_brightness: None,
+            brightness_task: None,
+            brightness_method: None,
+            brightness_probe: None,
             pre_suspend_command: None,
+            profile: None,
+            profile_actions: Vec::new(),
             resume_queue: Vec::new(),
             resume_commands_fired: false,
             shutdown_flag: Arc::new(Notify::new()),
+            soft_suspend_secs: None,
+            action_handlers: ActionHandlers::default(),
             start_time: now,
+            suspend_countdown: None,
             suspend_occured: false,
         };
+        state.action_index = state.first_timed_index();
 
         state
     }
@@ -158,9 +278,257 @@ impl ManagerState {
         }
     }
 
+    /// Start the countdown to an idle suspend and announce it; the notification is
+    /// sent in the background so a missing notification daemon never delays it
+    pub fn start_suspend_countdown(&mut self, secs: u64) {
+        let notification = Arc::new(AtomicU32::new(0));
+        self.suspend_countdown = Some(SuspendCountdown {
+            until: Instant::now() + Duration::from_secs(secs),
+            notification: Arc::clone(&notification),
+        });
+        log_message(&format!("Suspending in {}s unless there is activity", secs));
+        tokio::spawn(async move {
+            let body = format!("Suspending in {} seconds. Move the mouse or press a key to cancel.", secs);
+            match send_notification("Idle", &body, (secs * 1000).min(i32::MAX as u64) as i32).await {
+                Ok(id) => notification.store(id, Ordering::Relaxed),
+                Err(e) => log_debug_message(&format!("Could not show suspend countdown notification: {}", e)),
+            }
+        });
+    }
+
+    /// Drop a running suspend countdown and its notification. Returns true if one
+    /// was running.
+    pub fn cancel_suspend_countdown(&mut self) -> bool {
+        let Some(countdown) = self.suspend_countdown.take() else {
+            return false;
+        };
+        let id = countdown.notification.load(Ordering::Relaxed);
+        if id != 0 {
+            tokio::spawn(async move {
+                let _ = close_notification(id).await;
+            });
+        }
+        true
+    }
+
+    /// Stop an in-flight brightness command before another brightness write, so
+    /// two fades (or a fade and a restore) never interleave on the backlight.
+    /// Waits for the aborted task, whose command is killed when it is dropped.
+    pub async fn cancel_brightness_task(&mut self) {
+        if let Some(task) = self.brightness_task.take()
+            && !task.is_finished()
+        {
+            log_message("Cancelling in-flight brightness command");
+            task.abort();
+            let _ = task.await;
+        }
+    }
+
+    /// `action_shell` of the loaded config
+    pub fn action_shell(&self) -> ActionShell {
+        self.cfg.as_ref().map(|c| c.action_shell.clone()).unwrap_or_default()
+    }
+
+    /// Options for a hook command that belongs to no action
+    pub fn hook_options(&self) -> CommandOptions {
+        CommandOptions::with_shell(self.action_shell())
+    }
+
+    /// Record `event` in the JSONL event log, if `event_log` is set, tagged with
+    /// the active profile
+    pub fn log_event(&self, event: &str, fields: serde_json::Value) {
+        if let Some(path) = self.cfg.as_ref().and_then(|c| c.event_log.as_deref()) {
+            log_event(path, event, self.profile.as_deref(), fields);
+        }
+    }
+
+    /// Name of the action block that applies right now ("profile", "ac", "battery"
+    /// or "default")
+    pub fn active_block_name(&self) -> &'static str {
+        if self.profile.is_some() {
+            "profile"
+        } else if !self.ac_actions.is_empty() || !self.battery_actions.is_empty() {
+            match self.on_battery() {
+                Some(true) => "battery",
+                Some(false) => "ac",
+                None => "default",
+            }
+        } else {
+            "default"
+        }
+    }
+
+    /// Where the idle countdown starts over from after activity at `now`: now,
+    /// or the end of a snooze that is still running
+    pub fn activity_anchor(&self, now: Instant) -> Instant {
+        self.snoozed_until.map_or(now, |until| until.max(now))
+    }
+
+    /// Actions of the block that applies right now
+    pub fn active_actions(&self) -> &Vec<IdleActionBlock> {
+        match self.active_block_name() {
+            "profile" => &self.profile_actions,
+            "ac" => &self.ac_actions,
+            "battery" => &self.battery_actions,
+            _ => &self.default_actions,
+        }
+    }
+
+    /// Start of the sequence in the active block: its first timed action (instants
+    /// are fired separately), or past the end when it has none
+    pub fn first_timed_index(&self) -> usize {
+        let actions = self.active_actions();
+        actions.iter().position(|a| !a.is_instant()).unwrap_or(actions.len())
+    }
+
+    /// False for configs made only of instant (timeout 0) actions: once those have
+    /// fired there is nothing left for the idle loop to time
+    pub fn has_timed_actions(&self) -> bool {
+        self.default_actions.iter()
+            .chain(&self.ac_actions)
+            .chain(&self.battery_actions)
+            .chain(&self.profile_actions)
+            .any(|a| !a.is_instant())
+    }
+
+    /// Whether `action`'s prerequisite (`requires`) in the active block has fired;
+    /// a required lock must also still be active
+    pub fn requirement_met(&self, action: &IdleActionBlock) -> bool {
+        let Some(required) = &action.requires else {
+            return true;
+        };
+
+        match self.active_actions().iter().find(|a| a.base_name() == required) {
+            Some(prereq) if prereq.kind == IdleAction::LockScreen => {
+                prereq.last_triggered.is_some() && self.lock_state.is_locked
+            }
+            Some(prereq) => prereq.last_triggered.is_some(),
+            None => true,
+        }
+    }
+
+    /// End of the `relock_grace_seconds` window after the last unlock, if any
+    pub fn relock_grace_until(&self) -> Option<Instant> {
+        let grace = self.cfg.as_ref().map(|c| c.relock_grace_seconds).unwrap_or(0);
+        if grace == 0 {
+            return None;
+        }
+        self.lock_state.last_unlocked.map(|t| t + Duration::from_secs(grace))
+    }
+
+    /// When the lock action may fire again: after the relock grace period and
+    /// any backoff from a failed lock command
+    pub fn lock_hold_until(&self) -> Option<Instant> {
+        self.relock_grace_until().max(self.lock_state.retry_after)
+    }
+
+    /// Record a lock command that failed to spawn or exited right away. Retries
+    /// with exponential backoff, then disables the lock action so a broken
+    /// command can't restart the sequence in a tight loop.
+    pub fn record_lock_failure(&mut self, cause: &str) {
+        self.lock_state.failures += 1;
+        self.lock_state.child = None;
+        self.lock_state.is_locked = false;
+
+        let failures = self.lock_state.failures;
+        if failures >= LOCK_MAX_FAILURES {
+            self.lock_state.disabled = true;
+            self.lock_state.retry_after = None;
+            log_error_message(&format!(
+                "Lock command {} ({} failures in a row); disabling lock action until activity or reload. \
+                 Check that the lock_screen command exists and runs from a terminal",
+                cause, failures
+            ));
+        } else {
+            let delay = LOCK_RETRY_BASE * 2u32.pow(failures - 1);
+            self.lock_state.retry_after = Some(Instant::now() + delay);
+            log_message(&format!(
+                "Lock command {}, retrying in {}s (attempt {}/{})",
+                cause, delay.as_secs(), failures + 1, LOCK_MAX_FAILURES
+            ));
+
+            // Step back to the lock action so it is retried after the backoff
+            if let Some(i) = self.active_actions().iter().position(|a| a.kind == IdleAction::LockScreen)
+                && self.action_index > i
+            {
+                self.action_index = i;
+            }
+        }
+
+        self.notify.notify_one();
+    }
+
+    /// Forget lock failures (on activity): the next idle session tries again
+    pub fn clear_lock_failures(&mut self) {
+        self.lock_state.failures = 0;
+        self.lock_state.retry_after = None;
+        self.lock_state.disabled = false;
+    }
+
+    /// True once a timed action has fired since the last activity
+    pub fn fired_since_activity(&self) -> bool {
+        self.default_actions.iter()
+            .chain(&self.ac_actions)
+            .chain(&self.battery_actions)
+            .chain(&self.profile_actions)
+            .any(|a| a.last_triggered.is_some() && !a.is_instant())
+    }
+
+    /// `manual_only` actions of a block, named as in `block_actions`; only
+    /// `trigger` runs them
+    pub fn manual_actions(&self, block: &str) -> Vec<&IdleActionBlock> {
+        let Some(cfg) = &self.cfg else {
+            return Vec::new();
+        };
+        let profile_prefix = self.profile.as_ref().map(|p| format!("profile.{}.", p));
+        cfg.manual_actions
+            .iter()
+            .filter(|a| match block {
+                "ac" => a.name.starts_with("ac."),
+                "battery" => a.name.starts_with("battery."),
+                "profile" => profile_prefix.as_ref().is_some_and(|prefix| a.name.starts_with(prefix)),
+                "default" | "desktop" => a.base_name() == a.name,
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Actions of a block by name ("ac", "battery", "default" or "profile"), regardless of power state
+    pub fn block_actions(&self, block: &str) -> Option<&Vec<IdleActionBlock>> {
+        match block {
+            "ac" => Some(&self.ac_actions),
+            "battery" => Some(&self.battery_actions),
+            "default" | "desktop" => Some(&self.default_actions),
+            "profile" => Some(&self.profile_actions),
+            _ => None,
+        }
+    }
+
+    pub fn active_actions_mut(&mut self) -> &mut Vec<IdleActionBlock> {
+        match self.active_block_name() {
+            "profile" => &mut self.profile_actions,
+            "ac" => &mut self.ac_actions,
+            "battery" => &mut self.battery_actions,
+            _ => &mut self.default_actions,
+        }
+    }
+
+    /// Apply a reloaded config. Actions whose name, timeout and command are
+    /// unchanged keep their `last_triggered`; when everything that already fired
+    /// in the active block is unchanged, the countdown, idle state and a running
+    /// lock carry over too, so editing an unrelated line doesn't restart the
+    /// timers. Otherwise the sequence starts over.
     pub async fn update_from_config(&mut self, cfg: &StasisConfig) {
-        self.active_flags = ActiveFlags::default();
-        self.previous_brightness = None;
+        // Snapshot before the blocks are replaced
+        let old_actions: Vec<IdleActionBlock> = self.default_actions.iter()
+            .chain(&self.ac_actions)
+            .chain(&self.battery_actions)
+            .chain(&self.profile_actions)
+            .cloned()
+            .collect();
+        let old_block = self.active_actions().clone();
+        let old_instants = std::mem::take(&mut self.instant_actions);
+
         self.pre_suspend_command = cfg.pre_suspend_command.clone();
 
         // Split actions into blocks
@@ -190,10 +558,14 @@ impl ManagerState {
         self.ac_actions = ac_actions;
         self.battery_actions = battery_actions;
 
-        // Reset last_triggered for all actions
-        for actions in [&mut self.default_actions, &mut self.ac_actions, &mut self.battery_actions] {
-            for a in actions.iter_mut() {
-                a.last_triggered = None;
+        // Keep the selected profile if the new config still has it
+        match self.profile.as_ref().and_then(|name| cfg.profiles.get(name)) {
+            Some(actions) => self.profile_actions = actions.clone(),
+            None => {
+                if let Some(name) = self.profile.take() {
+                    log_message(&format!("Profile '{}' no longer exists, back to power-based actions", name));
+                }
+                self.profile_actions.clear();
             }
         }
 
@@ -203,32 +575,165 @@ impl ManagerState {
             .iter()
             .chain(&self.ac_actions)
             .chain(&self.battery_actions)
+            .chain(cfg.profiles.values().flatten())
             .filter(|a| a.is_instant())
             .cloned()
             .collect();
 
+        self.cfg = Some(Arc::new(cfg.clone()));
+        set_brightness_backend(self).await;
+
+        let new_block = self.active_actions();
+        let keep_timing = self.action_index <= new_block.len()
+            && (0..self.action_index).all(|i| old_block.get(i).is_some_and(|old| same_action(old, &new_block[i])));
+
+        if keep_timing {
+            for actions in [&mut self.default_actions, &mut self.ac_actions, &mut self.battery_actions, &mut self.profile_actions] {
+                for a in actions.iter_mut() {
+                    a.last_triggered = old_actions.iter()
+                        .find(|old| same_action(old, a))
+                        .and_then(|old| old.last_triggered);
+                }
+            }
+            // The current action's relative anchor is when the previous step fired,
+            // whatever changed about the action itself
+            let index = self.action_index;
+            if let (Some(old), Some(current)) = (old_block.get(index), self.active_actions_mut().get_mut(index))
+                && current.last_triggered.is_none()
+            {
+                current.last_triggered = old.last_triggered;
+            }
+
+            // Unchanged instants have done their job; changed ones fire again
+            let instants_unchanged = old_instants.len() == self.instant_actions.len()
+                && old_instants.iter().zip(&self.instant_actions).all(|(old, new)| same_action(old, new));
+            self.instants_triggered &= instants_unchanged;
+
+            // A running lock stays tracked; only the command to look for changes
+            self.lock_state.command = LockState::from_config(cfg).command;
+            // A lock disabled after failures gets another chance on any reload
+            self.clear_lock_failures();
+
+            // A countdown on screen carries on, unless its suspend is gone
+            if self.active_actions().get(self.action_index).is_none_or(|a| a.kind != IdleAction::Suspend) {
+                self.cancel_suspend_countdown();
+            }
+
+            self.notify.notify_one();
+            log_message(&format!(
+                "Idle timers kept across reload (at action {} of {})",
+                self.action_index,
+                self.active_actions().len()
+            ));
+            return;
+        }
+
+        // Reset last_triggered for all actions
+        for actions in [&mut self.default_actions, &mut self.ac_actions, &mut self.battery_actions, &mut self.profile_actions] {
+            for a in actions.iter_mut() {
+                a.last_triggered = None;
+            }
+        }
+
         // Reset instant trigger flag
         self.instants_triggered = false;
 
-        self.cfg = Some(Arc::new(cfg.clone()));
+        self.cancel_suspend_countdown();
+        // Keep the pre-dim brightness (and that it was captured) so a reload mid-dim
+        // still restores the original level on activity
+        let brightness_captured = self.active_flags.brightness_captured;
+        self.active_flags = ActiveFlags::default();
+        self.active_flags.brightness_captured = brightness_captured;
+
         self.lock_state = LockState::from_config(cfg);
         self.last_activity = Instant::now();
         self.last_activity_display = Instant::now();
+        self.snoozed_until = None;
 
         // Reset action index
-        self.action_index = 0;
+        self.action_index = self.first_timed_index();
+        self.idle_since = None;
 
         // Reset debounce according to new cfg
-        let debounce = Duration::from_secs(cfg.debounce_seconds as u64);
-        self.debounce = Some(Instant::now() + debounce);
+        self.debounce = Some(Instant::now() + cfg.debounce);
 
-        // Wake idle task to recalc immediately
+        // The idle loop has no fixed tick: it sleeps until `next_action_instant`,
+        // so waking it is enough for a shorter timeout to take effect right away
         self.notify.notify_one();
 
         log_message("Idle timers reloaded from config");
     }
 }
 
+/// Whether a reloaded action is the same one for timing purposes: same name,
+/// timeout and command
+fn same_action(old: &IdleActionBlock, new: &IdleActionBlock) -> bool {
+    old.name == new.name && old.timeout == new.timeout && old.session_command() == new.session_command()
+}
+
+/// Why idle timers are paused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InhibitReason {
+    Manual,
+    /// Identity of the playing player, when known
+    Media(Option<String>),
+    WaylandInhibitor,
+    App(String),
+    /// Load average above `inhibit_above_load`
+    Load,
+    /// A process listed in `inhibit_processes` is running
+    Process(String),
+    /// The focused window is fullscreen (`inhibit_fullscreen`)
+    Fullscreen,
+}
+
+impl InhibitReason {
+    /// Same kind of reason, ignoring payloads (e.g. any `App`)
+    pub fn same_kind(&self, other: &InhibitReason) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl std::fmt::Display for InhibitReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InhibitReason::Manual => write!(f, "manual"),
+            InhibitReason::Media(Some(player)) => write!(f, "media: {}", player),
+            InhibitReason::Media(None) => write!(f, "media"),
+            InhibitReason::WaylandInhibitor => write!(f, "wayland-inhibitor"),
+            InhibitReason::App(name) => write!(f, "app: {}", name),
+            InhibitReason::Load => write!(f, "load"),
+            InhibitReason::Process(name) => write!(f, "process: {}", name),
+            InhibitReason::Fullscreen => write!(f, "fullscreen"),
+        }
+    }
+}
+
+/// Rust code run in place of an action's command, see `Manager::register_handler`
+pub type ActionHandler = Box<dyn FnMut(&IdleActionBlock) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Registered handlers by action name
+#[derive(Default)]
+pub struct ActionHandlers(HashMap<String, ActionHandler>);
+
+impl ActionHandlers {
+    pub fn insert(&mut self, name: String, handler: ActionHandler) {
+        self.0.insert(name, handler);
+    }
+
+    /// Handler for `action`, by full name ("ac.dim") first, then base name ("dim")
+    pub fn get_mut(&mut self, action: &IdleActionBlock) -> Option<&mut ActionHandler> {
+        let key = if self.0.contains_key(&action.name) { action.name.as_str() } else { action.base_name() };
+        self.0.get_mut(key)
+    }
+}
+
+impl std::fmt::Debug for ActionHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
 #[derive(Debug)]
 pub enum ChassisType {
     Laptop(LaptopState),
@@ -252,37 +757,62 @@ impl Default for ChassisType {
 #[derive(Debug)]
 pub struct LockState {
     pub is_locked: bool,
-    pub pid: Option<u32>,
+    /// The lock command we launched, polled with `try_wait` for an exact unlock
+    /// and its exit status; None for a lock started elsewhere
+    pub child: Option<Child>,
     pub command: Option<String>,
     pub last_advanced: Option<Instant>,
     pub post_advanced: bool,
+    /// When the lock watcher last saw the lock end
+    pub last_unlocked: Option<Instant>,
+    /// Consecutive lock launches that failed to spawn or exited immediately
+    pub failures: u32,
+    /// Backoff after a failed launch: the lock action is held until then
+    pub retry_after: Option<Instant>,
+    /// Set after `LOCK_MAX_FAILURES` failures; lock actions are skipped until
+    /// activity or a config reload
+    pub disabled: bool,
 }
 
 impl Default for LockState {
     fn default() -> Self {
         Self {
             is_locked: false,
-            pid: None,
+            child: None,
             command: None,
             last_advanced: None,
             post_advanced: false,
+            last_unlocked: None,
+            failures: 0,
+            retry_after: None,
+            disabled: false,
         }
     }
 }
 
 impl LockState {
+    /// PID of the lock command we launched, while it has not been reaped; for
+    /// logs and external tools
+    pub fn pid(&self) -> Option<u32> {
+        self.child.as_ref().and_then(Child::id)
+    }
+
     pub fn from_config(cfg: &StasisConfig) -> Self {
         // Find the first LockScreen action (there should usually be one)
         let lock_action = cfg.actions.iter().find(|a| a.kind == IdleAction::LockScreen);
 
-        let command = lock_action.map(|a| a.command.clone());
+        let command = lock_action.map(|a| a.session_command().to_string());
 
         Self {
             is_locked: false,
-            pid: None,
+            child: None,
             command,
             last_advanced: None,
             post_advanced: false,
+            last_unlocked: None,
+            failures: 0,
+            retry_after: None,
+            disabled: false,
         }
     }
 }
@@ -302,3 +832,18 @@ impl Default for ActiveFlags {
     }
 }
 
+
+/// Session counters; monotonic until the daemon restarts (not cleared on reload)
+#[derive(Debug, Default)]
+pub struct ActionMetrics {
+    /// Times each action kind has fired, keyed by kind name
+    pub fired: BTreeMap<String, u64>,
+    /// Idle -> active transitions (activity after at least one action fired)
+    pub idle_cycles: u64,
+}
+
+impl ActionMetrics {
+    pub fn record_fired(&mut self, kind: &IdleAction) {
+        *self.fired.entry(kind.to_string()).or_insert(0) += 1;
+    }
+}
@@ -4,14 +4,24 @@ use tokio::process::Command;
 use serde_json::Value;
 use procfs::process::all_processes;
 
-use crate::config::model::StasisConfig;
+use crate::config::model::{AppInhibitPattern, StasisConfig};
 use crate::log::log_message;
-use crate::core::manager::Manager;
+use crate::core::manager::{InhibitReason, Manager};
+
+/// An open window as reported by the compositor
+struct WindowInfo {
+    app_id: String,
+    title: String,
+    /// Focused and fullscreen; only Hyprland reports this
+    fullscreen: bool,
+}
 
 /// Tracks currently running apps to inhibit idle
 pub struct AppInhibitor {
     cfg: Arc<StasisConfig>,
     active_apps: HashSet<String>,
+    /// The focused window was fullscreen on the last check (`inhibit_fullscreen`)
+    fullscreen: bool,
     desktop: String,
     #[allow(dead_code)]
     manager: Arc<Mutex<Manager>>,
@@ -28,6 +38,7 @@ impl AppInhibitor {
         Self {
             cfg,
             active_apps: HashSet::new(),
+            fullscreen: false,
             desktop,
             manager,
         }
@@ -36,15 +47,22 @@ impl AppInhibitor {
     /// Returns true if any app in inhibit_apps is currently running
     pub async fn is_any_app_running(&mut self) -> bool {
         let mut new_active_apps = HashSet::new();
+        let mut fullscreen = false;
 
         let running = match self.check_compositor_windows().await {
-            Ok(result_apps) => {
+            Ok((result_apps, result_fullscreen)) => {
                 new_active_apps = result_apps;
+                fullscreen = result_fullscreen;
                 !new_active_apps.is_empty()
             },
             Err(_) => self.check_processes_with_tracking(&mut new_active_apps),
         };
 
+        if fullscreen && !self.fullscreen {
+            log_message("Fullscreen inhibit active");
+        }
+        self.fullscreen = fullscreen;
+
         for app in &new_active_apps {
             if !self.active_apps.contains(app) {
                 log_message(&format!("App inhibit active: {}", app));
@@ -77,13 +95,12 @@ impl AppInhibitor {
                 Err(_) => continue,
             };
 
-            // Compare against inhibit patterns
-            for pattern in &self.cfg.inhibit_apps {
+            // Compare against inhibit patterns; process names stand in for
+            // app_ids here, titles aren't available without the compositor
+            for pattern in self.cfg.inhibit_apps.iter().chain(&self.cfg.inhibit_app_ids) {
                 let matched = match pattern {
-                    crate::config::model::AppInhibitPattern::Literal(s) => {
-                        proc_name.eq_ignore_ascii_case(s)
-                    }
-                    crate::config::model::AppInhibitPattern::Regex(r) => r.is_match(&proc_name),
+                    AppInhibitPattern::Literal(s) => proc_name.eq_ignore_ascii_case(s),
+                    AppInhibitPattern::Regex(r) => r.is_match(&proc_name),
                 };
 
                 if matched {
@@ -97,36 +114,56 @@ impl AppInhibitor {
         any_running
     }    
     
-    /// Check compositor windows via IPC
-    async fn check_compositor_windows(&self) -> Result<HashSet<String>, Box<dyn std::error::Error + Send + Sync>> {
-        match self.desktop.as_str() {
-            "niri" => {
-                let app_ids = self.try_niri_ipc().await?;
-                Ok(app_ids.into_iter()
-                    .filter(|app| self.should_inhibit_for_app(app))
-                    .collect())
-            }
-            "hyprland" => {
-                let windows = self.try_hyprland_ipc().await?;
-                Ok(windows.into_iter()
-                    .filter_map(|win| win.get("app_id").and_then(|v| v.as_str()).map(|s| s.to_string()))
-                    .filter(|app| self.should_inhibit_for_app(app))
-                    .collect())
-            }
-            _ => Err("No IPC available, fallback to process scan".into())
-        }
+    /// Whether the focused window was fullscreen on the last check
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Check compositor windows via IPC: the inhibiting apps, and whether the
+    /// focused window is fullscreen (when `inhibit_fullscreen` is set)
+    async fn check_compositor_windows(&self) -> Result<(HashSet<String>, bool), Box<dyn std::error::Error + Send + Sync>> {
+        let windows = match self.desktop.as_str() {
+            "niri" => self.try_niri_ipc().await?,
+            "hyprland" => self.try_hyprland_ipc().await?
+                .into_iter()
+                .map(|win| {
+                    let field = |name: &str| win.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                    // `fullscreen` is a bool on older Hyprland, a mode on newer
+                    // ones (0 none, 1 maximized, 2 fullscreen, 3 both)
+                    let fullscreen = win.get("fullscreen")
+                        .is_some_and(|v| v.as_bool().unwrap_or(false) || v.as_u64().is_some_and(|m| m >= 2));
+                    let focused = win.get("focusHistoryID").and_then(|v| v.as_i64()) == Some(0);
+                    WindowInfo { app_id: field("app_id"), title: field("title"), fullscreen: fullscreen && focused }
+                })
+                .collect(),
+            _ => return Err("No IPC available, fallback to process scan".into()),
+        };
+
+        let fullscreen = self.cfg.inhibit_fullscreen && windows.iter().any(|win| win.fullscreen);
+        Ok((windows.iter().filter_map(|win| self.inhibiting_window(win)).collect(), fullscreen))
     }
 
-    async fn try_niri_ipc(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn try_niri_ipc(&self) -> Result<Vec<WindowInfo>, Box<dyn std::error::Error + Send + Sync>> {
         let output = Command::new("niri").args(&["msg", "windows"]).output().await?;
         if !output.status.success() {
             return Err(format!("niri command failed: {}", String::from_utf8_lossy(&output.stderr)).into());
         }
         let text = String::from_utf8(output.stdout)?;
-        Ok(text.lines()
-            .filter_map(|line| line.strip_prefix("  App ID: "))
-            .map(|s| s.trim_matches('"').to_string())
-            .collect())
+
+        // Each window starts with a "Window ID" header followed by indented fields
+        let mut windows: Vec<WindowInfo> = Vec::new();
+        for line in text.lines() {
+            if line.starts_with("Window ID") {
+                windows.push(WindowInfo { app_id: String::new(), title: String::new(), fullscreen: false });
+            } else if let Some(win) = windows.last_mut() {
+                if let Some(app_id) = line.strip_prefix("  App ID: ") {
+                    win.app_id = app_id.trim_matches('"').to_string();
+                } else if let Some(title) = line.strip_prefix("  Title: ") {
+                    win.title = title.trim_matches('"').to_string();
+                }
+            }
+        }
+        Ok(windows)
     }
 
     async fn try_hyprland_ipc(&self) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
@@ -146,15 +183,28 @@ impl AppInhibitor {
         Ok(windows)
     }
 
-    fn should_inhibit_for_app(&self, app_id: &str) -> bool {
-        for pattern in &self.cfg.inhibit_apps {
-            let matched = match pattern {
-                crate::config::model::AppInhibitPattern::Literal(s) => self.app_id_matches(s, app_id),
-                crate::config::model::AppInhibitPattern::Regex(r) => r.is_match(app_id),
-            };
-            if matched { return true; }
+    /// Returns the name to report if this window should inhibit idle.
+    /// `inhibit_apps` is checked against both fields, `inhibit_app_ids` and
+    /// `inhibit_titles` only against their own
+    fn inhibiting_window(&self, win: &WindowInfo) -> Option<String> {
+        let app_id_hit = !win.app_id.is_empty() && self.cfg.inhibit_apps.iter()
+            .chain(&self.cfg.inhibit_app_ids)
+            .any(|p| self.pattern_matches_app_id(p, &win.app_id));
+        if app_id_hit {
+            return Some(win.app_id.clone());
+        }
+
+        let title_hit = !win.title.is_empty()
+            && (self.cfg.inhibit_apps.iter().any(|p| self.pattern_matches_app_id(p, &win.title))
+                || self.cfg.inhibit_titles.iter().any(|p| p.matches_word(&win.title)));
+        title_hit.then(|| format!("{} ({})", win.title, win.app_id))
+    }
+
+    fn pattern_matches_app_id(&self, pattern: &AppInhibitPattern, app_id: &str) -> bool {
+        match pattern {
+            AppInhibitPattern::Literal(s) => self.app_id_matches(s, app_id),
+            AppInhibitPattern::Regex(r) => r.is_match(app_id),
         }
-        false
     }
 
     fn app_id_matches(&self, pattern: &str, app_id: &str) -> bool {
@@ -173,6 +223,7 @@ impl AppInhibitor {
     pub async fn shutdown(&mut self) {
         log_message("Shutting down app inhibitor...");
         self.active_apps.clear();
+        self.fullscreen = false;
     }
 }
 
@@ -184,8 +235,12 @@ pub async fn spawn_app_inhibit_task(
  
 
     // If no inhibit apps are configured, sleep forever
-    if cfg.inhibit_apps.is_empty() {
-        log_message("No inhibit_apps configured, sleeping app inhibitor.");
+    if cfg.inhibit_apps.is_empty()
+        && cfg.inhibit_app_ids.is_empty()
+        && cfg.inhibit_titles.is_empty()
+        && !cfg.inhibit_fullscreen
+    {
+        log_message("No inhibit_apps, inhibit_app_ids, inhibit_titles or inhibit_fullscreen configured, sleeping app inhibitor.");
         tokio::spawn(async move {
             futures::future::pending::<()>().await; // sleep indefinitely
         });
@@ -202,9 +257,18 @@ pub async fn spawn_app_inhibit_task(
 
                 let mut mgr = guard.manager.lock().await;
                 if any_running && !was_running {
-                    mgr.pause(false).await;
+                    let app = guard.active_apps.iter().next().cloned().unwrap_or_default();
+                    mgr.pause(InhibitReason::App(app)).await;
                 } else if !any_running && was_running {
-                    mgr.resume(false).await;
+                    mgr.resume(InhibitReason::App(String::new())).await;
+                }
+
+                // Compared with the manager, since an IPC `info` also refreshes the inhibitor
+                let paused_fullscreen = mgr.state.inhibit_reasons.contains(&InhibitReason::Fullscreen);
+                if guard.fullscreen && !paused_fullscreen {
+                    mgr.pause(InhibitReason::Fullscreen).await;
+                } else if !guard.fullscreen && paused_fullscreen {
+                    mgr.resume(InhibitReason::Fullscreen).await;
                 }
             }
             tokio::time::sleep(std::time::Duration::from_secs(4)).await;
@@ -42,6 +42,72 @@ pub async fn listen_for_suspend_events(idle_manager: Arc<Mutex<Manager>>) -> Zbu
     Ok(())
 }
 
+/// logind's `LockedHint` for the session stasis runs in
+pub async fn session_locked_hint() -> ZbusResult<bool> {
+    let connection = Connection::system().await?;
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        "org.freedesktop.login1.Session"
+    ).await?;
+    Ok(proxy.get_property::<bool>("LockedHint").await?)
+}
+
+/// Remote logind sessions (SSH and the like), as "user@host" / "user"
+pub async fn remote_sessions() -> ZbusResult<Vec<String>> {
+    let connection = Connection::system().await?;
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager"
+    ).await?;
+
+    let sessions: Vec<(String, u32, String, String, zvariant::OwnedObjectPath)> =
+        manager.call("ListSessions", &()).await?;
+
+    let mut remote = Vec::new();
+    for (_, _, user, _, path) in sessions {
+        let session = Proxy::new(
+            &connection,
+            "org.freedesktop.login1",
+            path,
+            "org.freedesktop.login1.Session"
+        ).await?;
+        if session.get_property::<bool>("Remote").await.unwrap_or(false) {
+            let host = session.get_property::<String>("RemoteHost").await.unwrap_or_default();
+            remote.push(if host.is_empty() { user } else { format!("{}@{}", user, host) });
+        }
+    }
+    Ok(remote)
+}
+
+/// Show a desktop notification through `org.freedesktop.Notifications`, expiring
+/// after `expire_ms`. Returns its id for `close_notification`.
+pub async fn send_notification(summary: &str, body: &str, expire_ms: i32) -> ZbusResult<u32> {
+    let connection = Connection::session().await?;
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications"
+    ).await?;
+    let hints: HashMap<&str, zvariant::Value> = HashMap::from([("urgency", zvariant::Value::U8(2))]);
+    Ok(proxy.call("Notify", &("Stasis", 0u32, "system-suspend", summary, body, Vec::<&str>::new(), hints, expire_ms)).await?)
+}
+
+pub async fn close_notification(id: u32) -> ZbusResult<()> {
+    let connection = Connection::session().await?;
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications"
+    ).await?;
+    Ok(proxy.call("CloseNotification", &(id,)).await?)
+}
+
 pub async fn listen_for_lid_events(idle_manager: Arc<Mutex<Manager>>) -> ZbusResult<()> {
     let connection = Connection::system().await?;
 
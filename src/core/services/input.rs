@@ -9,7 +9,10 @@ use std::{
     time::{Duration, Instant},
 };
 
-use input::LibinputInterface;
+use input::{
+    event::{pointer::PointerEvent, EventTrait},
+    Event, LibinputInterface,
+};
 use tokio::sync::Mutex;
 use futures::FutureExt; // for now_or_never()
 use crate::{core::manager::Manager, log::log_message};
@@ -35,7 +38,7 @@ impl LibinputInterface for InputDetection {
 pub fn spawn_input_task(manager: Arc<Mutex<Manager>>) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         // Channel: blocking thread → async task
-        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
         let manager_clone = Arc::clone(&manager);
 
         // Async listener: reacts to input events
@@ -48,14 +51,14 @@ pub fn spawn_input_task(manager: Arc<Mutex<Manager>>) -> tokio::task::JoinHandle
 
                 tokio::select! {
                     maybe_event = rx.recv() => {
-                        if maybe_event.is_none() {
+                        let Some(device) = maybe_event else {
                             log_message("Input async handler channel closed");
                             break;
-                        }
+                        };
 
                         crate::core::events::handlers::handle_event(
                             &manager_clone,
-                            crate::core::events::handlers::Event::InputActivity,
+                            crate::core::events::handlers::Event::InputActivity { device },
                         ).await;
                     }
 
@@ -81,6 +84,16 @@ pub fn spawn_input_task(manager: Arc<Mutex<Manager>>) -> tokio::task::JoinHandle
             let mut last_reset = Instant::now();
             const DEBOUNCE: Duration = Duration::from_millis(300);
 
+            // Lowercased ignore_input_devices, refreshed whenever the manager is free
+            let mut ignored_devices: Vec<String> = Vec::new();
+            let mut min_movement = 0.0_f64;
+
+            // Pointer motion accumulated since the last counted activity; a pause in
+            // motion longer than MOTION_WINDOW starts the accumulation over
+            let mut accumulated_motion = 0.0_f64;
+            let mut last_motion = Instant::now();
+            const MOTION_WINDOW: Duration = Duration::from_secs(1);
+
             loop {
                 // Check for shutdown signal without blocking
                 if let Ok(mgr) = manager_for_thread.try_lock() {
@@ -88,6 +101,10 @@ pub fn spawn_input_task(manager: Arc<Mutex<Manager>>) -> tokio::task::JoinHandle
                         eprintln!("Input thread shutting down...");
                         break;
                     }
+                    if let Some(cfg) = &mgr.state.cfg {
+                        ignored_devices = cfg.ignore_input_devices.iter().map(|d| d.to_lowercase()).collect();
+                        min_movement = cfg.min_activity_movement;
+                    }
                 }
 
                 // Poll with 2-second timeout
@@ -104,16 +121,42 @@ pub fn spawn_input_task(manager: Arc<Mutex<Manager>>) -> tokio::task::JoinHandle
                     continue;
                 }
 
-                // Only process the **first event** per iteration to reduce CPU
-                if libinput.next().is_some() {
+                // Only act on the first event from a device that is not ignored and,
+                // for relative pointer motion, once min_activity_movement is reached;
+                // the rest of the batch is drained without processing.
+                // Key presses, clicks and scrolling always count.
+                let device = libinput.by_ref().find_map(|event| {
+                    let name = event.device().name().to_string();
+                    let lower = name.to_lowercase();
+                    if ignored_devices.iter().any(|d| lower.contains(d.as_str())) {
+                        return None;
+                    }
+
+                    if min_movement > 0.0 {
+                        if let Event::Pointer(PointerEvent::Motion(motion)) = &event {
+                            let now = Instant::now();
+                            if now.duration_since(last_motion) > MOTION_WINDOW {
+                                accumulated_motion = 0.0;
+                            }
+                            last_motion = now;
+                            accumulated_motion += motion.dx().hypot(motion.dy());
+                            if accumulated_motion < min_movement {
+                                return None;
+                            }
+                        }
+                        accumulated_motion = 0.0;
+                    }
+
+                    Some(name)
+                });
+                while libinput.next().is_some() {}
+
+                if let Some(device) = device {
                     let now = Instant::now();
                     if now.duration_since(last_reset) >= DEBOUNCE {
                         last_reset = now;
-                        let _ = tx.send(()); // Notify async task
+                        let _ = tx.send(device); // Notify async task
                     }
-
-                    // Drain remaining events without processing
-                    while libinput.next().is_some() {}
                 }
             }
         });
@@ -0,0 +1,58 @@
+use std::{fs, sync::Arc, time::Duration};
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::{
+    core::manager::{helpers::wake_idle_tasks, InhibitReason, Manager},
+    log::{log_error_message, log_message},
+};
+
+/// How often `/proc/loadavg` is checked; the kernel updates it every 5 seconds
+const LOAD_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 1-minute load average
+fn read_load_average() -> Option<f64> {
+    fs::read_to_string("/proc/loadavg")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Inhibit idle while the 1-minute load average is above `threshold` (a build,
+/// an encode...), through the same pause reasons as media and apps
+pub fn spawn_load_monitor(manager: Arc<Mutex<Manager>>, threshold: f64) {
+    tokio::spawn(async move {
+        if read_load_average().is_none() {
+            log_error_message("Cannot read /proc/loadavg, load monitor disabled");
+            return;
+        }
+        let shutdown = manager.lock().await.state.shutdown_flag.clone();
+        let mut inhibiting = false;
+
+        log_message(&format!("Load monitor started (inhibit above {:.2})", threshold));
+
+        loop {
+            if let Some(load) = read_load_average() {
+                let busy = load > threshold;
+                if busy != inhibiting {
+                    inhibiting = busy;
+                    let mut mgr = manager.lock().await;
+                    if busy {
+                        log_message(&format!("Load {:.2} above {:.2}, inhibiting idle", load, threshold));
+                        mgr.pause(InhibitReason::Load).await;
+                    } else {
+                        log_message(&format!("Load {:.2} back under {:.2}", load, threshold));
+                        mgr.resume(InhibitReason::Load).await;
+                        wake_idle_tasks(&mgr.state);
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = sleep(LOAD_POLL_INTERVAL) => {}
+                _ = shutdown.notified() => return,
+            }
+        }
+    });
+}
@@ -1,16 +1,34 @@
 // Optimized media.rs - D-Bus signal monitoring with zbus 5.x
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use eyre::Result;
 use mpris::{PlayerFinder, PlaybackStatus};
 use tokio::task;
-use crate::core::manager::Manager;
+use crate::{config::model::AppInhibitPattern, core::manager::{InhibitReason, Manager}};
 
+/// Remote/network players ignored by `ignore_remote_media`, matched as whole words
 const IGNORED_PLAYERS: &[&str] = &[
-    "KDE Connect", "kdeconnect", "Chromecast", "chromecast",
+    "KDE Connect", "kdeconnect", "Chromecast",
     "Spotify Connect", "spotifyd", "vlc-http", "plexamp", "bluez",
 ];
 
+/// Upper bound for one MPRIS enumeration; a player that hangs answering `GetAll`
+/// must not stall the monitor
+const MEDIA_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Built-in remote players plus the configured `ignored_players`
+fn ignored_player_patterns(extra: &[AppInhibitPattern]) -> Vec<AppInhibitPattern> {
+    IGNORED_PLAYERS
+        .iter()
+        .map(|s| AppInhibitPattern::Literal(s.to_string()))
+        .chain(extra.iter().cloned())
+        .collect()
+}
+
+fn is_ignored_player(ignored: &[AppInhibitPattern], identity: &str, bus_name: &str) -> bool {
+    ignored.iter().any(|p| p.matches_word(identity) || p.matches_word(bus_name))
+}
+
 // Event-driven media monitoring using D-Bus signals
 use zbus::{Connection, MatchRule, MessageStream};
 use futures_util::stream::StreamExt;
@@ -18,7 +36,9 @@ use futures_util::stream::StreamExt;
 pub async fn spawn_media_monitor_dbus(
     manager: Arc<tokio::sync::Mutex<Manager>>,
     ignore_remote_media: bool,
+    ignored_players: Vec<AppInhibitPattern>,
 ) -> Result<()> {
+    let querier = MediaQuerier::spawn(ignore_remote_media, ignored_player_patterns(&ignored_players));
     task::spawn(async move {
         let conn = match Connection::session().await {
             Ok(c) => c,
@@ -49,10 +69,11 @@ pub async fn spawn_media_monitor_dbus(
         let mut media_playing = false;
         
         // Also do an initial check
-        let any_playing = check_media_playing(ignore_remote_media);
-        if any_playing {
+        let playing = querier.query().await.and_then(Result::ok).flatten();
+        if playing.is_some() {
             let mut mgr = manager.lock().await;
-            mgr.pause(false).await;
+            mgr.state.media_player = playing;
+            mgr.pause(InhibitReason::Media(None)).await;
             media_playing = true;
         }
         
@@ -60,14 +81,23 @@ pub async fn spawn_media_monitor_dbus(
             // Wait for D-Bus signal - 0% CPU while waiting!
             if let Some(_msg) = stream.next().await {
                 // Check all players when we get a PropertiesChanged signal
-                let any_playing = check_media_playing(ignore_remote_media);
+                let playing = match querier.query().await {
+                    Some(Ok(playing)) => playing,
+                    Some(Err(e)) => {
+                        crate::log::log_error_message(&e);
+                        continue;
+                    }
+                    None => continue,
+                };
+                let any_playing = playing.is_some();
                 
                 let mut mgr = manager.lock().await;
+                mgr.state.media_player = playing;
                 if any_playing && !media_playing {
-                    mgr.pause(false).await;
+                    mgr.pause(InhibitReason::Media(None)).await;
                     media_playing = true;
                 } else if !any_playing && media_playing {
-                    mgr.resume(false).await;
+                    mgr.media_stopped().await;
                     media_playing = false;
                 }
             }
@@ -76,36 +106,102 @@ pub async fn spawn_media_monitor_dbus(
     Ok(())
 }
 
-fn check_media_playing(ignore_remote_media: bool) -> bool {
-    match PlayerFinder::new() {
-        Ok(finder) => match finder.find_all() {
-            Ok(players) => players.iter().any(|player| {
-                let identity = player.identity();
-                let bus_name = player.bus_name().to_string();
-                let is_playing = player.get_playback_status()
-                    .map(|s| s == PlaybackStatus::Playing)
-                    .unwrap_or(false);
-                
-                if !is_playing { return false; }
-                
-                if ignore_remote_media {
-                    !IGNORED_PLAYERS.iter().any(|s| identity.contains(s) || bus_name.contains(s))
-                } else {
-                    true
+/// Reply to one MPRIS check: the playing player, or why enumerating failed
+type MediaReply = Result<Option<String>, String>;
+
+/// Owns the MPRIS `PlayerFinder` on a dedicated thread (it is not `Send`), so
+/// one D-Bus connection serves every check instead of a new one each time. The
+/// finder is rebuilt on the next check after it fails.
+struct MediaQuerier {
+    requests: std::sync::mpsc::Sender<tokio::sync::oneshot::Sender<MediaReply>>,
+}
+
+impl MediaQuerier {
+    fn spawn(ignore_remote_media: bool, ignored: Vec<AppInhibitPattern>) -> Self {
+        let (requests, rx) = std::sync::mpsc::channel::<tokio::sync::oneshot::Sender<MediaReply>>();
+        let spawned = std::thread::Builder::new()
+            .name("stasis-mpris".to_string())
+            .spawn(move || {
+                let mut finder = None;
+                for reply in rx {
+                    let _ = reply.send(check_media_playing(&mut finder, ignore_remote_media, &ignored));
                 }
-            }),
-            Err(_) => false,
-        },
-        Err(_) => false,
+            });
+        if let Err(e) = spawned {
+            crate::log::log_error_message(&format!("Failed to start MPRIS thread: {}", e));
+        }
+        Self { requests }
     }
+
+    /// Run one check, bounded by `MEDIA_QUERY_TIMEOUT`. Returns None when it timed
+    /// out or the thread is gone, meaning "state unchanged".
+    async fn query(&self) -> Option<MediaReply> {
+        let (reply, rx) = tokio::sync::oneshot::channel();
+        if self.requests.send(reply).is_err() {
+            return None;
+        }
+        match tokio::time::timeout(MEDIA_QUERY_TIMEOUT, rx).await {
+            Ok(Ok(result)) => Some(result),
+            Ok(Err(_)) => {
+                crate::log::log_error_message("MPRIS query thread exited");
+                None
+            }
+            Err(_) => {
+                crate::log::log_error_message(&format!(
+                    "MPRIS enumeration timed out after {}s, keeping previous media state",
+                    MEDIA_QUERY_TIMEOUT.as_secs()
+                ));
+                None
+            }
+        }
+    }
+}
+
+/// Identity of the first playing player that counts as local media. Connects
+/// `finder` when it is empty and drops it when listing fails, so a stale
+/// connection is replaced on the next check.
+fn check_media_playing(
+    finder: &mut Option<PlayerFinder>,
+    ignore_remote_media: bool,
+    ignored: &[AppInhibitPattern],
+) -> MediaReply {
+    let players = match finder {
+        Some(finder) => finder.find_all(),
+        None => {
+            let new = PlayerFinder::new().map_err(|e| format!("MPRIS: failed to create finder: {:?}", e))?;
+            finder.insert(new).find_all()
+        }
+    };
+    let players = players.map_err(|e| {
+        *finder = None;
+        format!("MPRIS: failed to list players: {:?}", e)
+    })?;
+
+    Ok(players.iter().find(|player| {
+        let identity = player.identity();
+        let bus_name = player.bus_name().to_string();
+        let is_playing = player.get_playback_status()
+            .map(|s| s == PlaybackStatus::Playing)
+            .unwrap_or(false);
+
+        if !is_playing { return false; }
+
+        if ignore_remote_media {
+            !is_ignored_player(ignored, identity, &bus_name)
+        } else {
+            true
+        }
+    }).map(|player| player.identity().to_string()))
 }
 
 // FALLBACK: Polling version with long intervals (if D-Bus approach has issues)
 pub fn spawn_media_monitor_polling(
     manager: Arc<tokio::sync::Mutex<Manager>>,
     ignore_remote_media: bool,
+    ignored_players: Vec<AppInhibitPattern>,
 ) -> Result<()> {
     let manager_clone = Arc::clone(&manager);
+    let querier = MediaQuerier::spawn(ignore_remote_media, ignored_player_patterns(&ignored_players));
     task::spawn(async move {
         let mut media_playing = false;
         let mut last_error: Option<String> = None;
@@ -120,52 +216,29 @@ pub fn spawn_media_monitor_polling(
             
             tokio::time::sleep(sleep_duration).await;
             
-            let any_playing = match PlayerFinder::new() {
-                Ok(finder) => match finder.find_all() {
-                    Ok(players) => {
-                        last_error = None;
-                        players.iter().any(|player| {
-                            let identity = player.identity();
-                            let bus_name = player.bus_name().to_string();
-                            let is_playing = player.get_playback_status()
-                                .map(|s| s == PlaybackStatus::Playing)
-                                .unwrap_or(false);
-                            
-                            if !is_playing { return false; }
-                            
-                            if ignore_remote_media {
-                                if IGNORED_PLAYERS.iter().any(|s| identity.contains(s) || bus_name.contains(s)) {
-                                    return false;
-                                }
-                            }
-                            true
-                        })
-                    }
-                    Err(e) => {
-                        let msg = format!("MPRIS: failed to list players: {:?}", e);
-                        if last_error.as_ref() != Some(&msg) {
-                            crate::log::log_error_message(&msg);
-                            last_error = Some(msg);
-                        }
-                        false
-                    }
-                },
-                Err(e) => {
-                    let msg = format!("MPRIS: failed to create finder: {:?}", e);
+            let playing = match querier.query().await {
+                Some(Ok(playing)) => {
+                    last_error = None;
+                    playing
+                }
+                Some(Err(msg)) => {
                     if last_error.as_ref() != Some(&msg) {
                         crate::log::log_error_message(&msg);
                         last_error = Some(msg);
                     }
-                    false
+                    None
                 }
+                None => continue,
             };
+            let any_playing = playing.is_some();
             
             let mut mgr = manager_clone.lock().await;
+            mgr.state.media_player = playing;
             if any_playing && !media_playing {
-                mgr.pause(false).await;
+                mgr.pause(InhibitReason::Media(None)).await;
                 media_playing = true;
             } else if !any_playing && media_playing {
-                mgr.resume(false).await;
+                mgr.media_stopped().await;
                 media_playing = false;
             }
         }
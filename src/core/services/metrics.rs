@@ -0,0 +1,91 @@
+use std::{fmt::Write as _, sync::Arc};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::Mutex,
+};
+
+use crate::core::manager::Manager;
+use crate::log::{log_error_message, log_message};
+
+/// Serve counters in Prometheus text format on `addr` (e.g. "127.0.0.1:9101").
+/// Only `GET /metrics` is answered; everything else gets a 404.
+pub async fn spawn_metrics_server(manager: Arc<Mutex<Manager>>, addr: String) {
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            log_error_message(&format!("Failed to bind metrics endpoint on {}: {}", addr, e));
+            return;
+        }
+    };
+
+    log_message(&format!("Metrics endpoint listening on http://{}/metrics", addr));
+
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    log_error_message(&format!("Failed to accept metrics connection: {}", e));
+                    continue;
+                }
+            };
+
+            let manager = Arc::clone(&manager);
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let n = match stream.read(&mut buf).await {
+                    Ok(n) => n,
+                    Err(_) => return,
+                };
+
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let path = request.split_whitespace().nth(1).unwrap_or("");
+
+                let response = if request.starts_with("GET ") && path == "/metrics" {
+                    let body = render_metrics(&manager).await;
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                };
+
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+}
+
+async fn render_metrics(manager: &Arc<Mutex<Manager>>) -> String {
+    let mgr = manager.lock().await;
+    let state = &mgr.state;
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# HELP stasis_idle_seconds Seconds since the last user activity.");
+    let _ = writeln!(out, "# TYPE stasis_idle_seconds gauge");
+    let _ = writeln!(out, "stasis_idle_seconds {}", state.last_activity_display.elapsed().as_secs_f64());
+
+    let inhibited = state.paused || state.manually_paused;
+    let _ = writeln!(out, "# HELP stasis_inhibited Whether idle actions are currently inhibited.");
+    let _ = writeln!(out, "# TYPE stasis_inhibited gauge");
+    let _ = writeln!(out, "stasis_inhibited {}", inhibited as u8);
+
+    let _ = writeln!(out, "# HELP stasis_on_battery Whether the machine runs on battery (always 0 on desktops).");
+    let _ = writeln!(out, "# TYPE stasis_on_battery gauge");
+    let _ = writeln!(out, "stasis_on_battery {}", state.on_battery().unwrap_or(false) as u8);
+
+    let _ = writeln!(out, "# HELP stasis_actions_fired_total Idle actions fired since start, by kind.");
+    let _ = writeln!(out, "# TYPE stasis_actions_fired_total counter");
+    for (kind, count) in &state.metrics.fired {
+        let _ = writeln!(out, "stasis_actions_fired_total{{kind=\"{}\"}} {}", kind, count);
+    }
+
+    let _ = writeln!(out, "# HELP stasis_idle_cycles_total Idle to active transitions since start.");
+    let _ = writeln!(out, "# TYPE stasis_idle_cycles_total counter");
+    let _ = writeln!(out, "stasis_idle_cycles_total {}", state.metrics.idle_cycles);
+
+    out
+}
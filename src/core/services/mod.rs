@@ -1,6 +1,10 @@
 pub mod app_inhibit;
 pub mod dbus;
 pub mod input;
+pub mod load;
 pub mod media;
+pub mod metrics;
+pub mod network;
 pub mod power_detection;
+pub mod processes;
 pub mod wayland;
@@ -0,0 +1,66 @@
+use std::{fs, sync::Arc, time::{Duration, Instant}};
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::{
+    core::{events::handlers::{handle_event, Event}, manager::Manager},
+    log::{log_error_message, log_message},
+};
+
+/// How often `/proc/net/dev` is sampled
+const NETWORK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Total bytes received plus sent on every interface except loopback
+fn read_network_bytes() -> Option<u64> {
+    let content = fs::read_to_string("/proc/net/dev").ok()?;
+    let total = content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (iface, counters) = line.split_once(':')?;
+            if iface.trim() == "lo" {
+                return None;
+            }
+            let fields: Vec<u64> = counters
+                .split_whitespace()
+                .filter_map(|f| f.parse().ok())
+                .collect();
+            // Receive bytes is the first counter, transmit bytes the ninth
+            Some(fields.first()? + fields.get(8)?)
+        })
+        .sum();
+    Some(total)
+}
+
+/// Treat network throughput above `threshold_kbps` as user activity, for
+/// headless machines without input devices (e.g. an active SSH session)
+pub fn spawn_network_monitor(manager: Arc<Mutex<Manager>>, threshold_kbps: u64) {
+    tokio::spawn(async move {
+        let Some(mut last_bytes) = read_network_bytes() else {
+            log_error_message("Cannot read /proc/net/dev, network activity monitor disabled");
+            return;
+        };
+        let mut last_sample = Instant::now();
+        let shutdown = manager.lock().await.state.shutdown_flag.clone();
+
+        log_message(&format!("Network activity monitor started (threshold {} kbps)", threshold_kbps));
+
+        loop {
+            tokio::select! {
+                _ = sleep(NETWORK_POLL_INTERVAL) => {}
+                _ = shutdown.notified() => return,
+            }
+
+            let Some(bytes) = read_network_bytes() else { continue };
+            let elapsed = last_sample.elapsed().as_secs_f64();
+            // Counters go backwards when an interface disappears; skip that sample
+            let delta = bytes.saturating_sub(last_bytes);
+            last_bytes = bytes;
+            last_sample = Instant::now();
+
+            let kbps = (delta as f64 * 8.0 / 1000.0 / elapsed) as u64;
+            if kbps > threshold_kbps {
+                handle_event(&manager, Event::NetworkActivity { kbps }).await;
+            }
+        }
+    });
+}
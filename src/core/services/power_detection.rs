@@ -1,8 +1,8 @@
 use std::{fs, sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 
-use crate::core::manager::Manager;
-use crate::log::log_message;
+use crate::core::manager::{actions::run_command_detached_with, Manager};
+use crate::log::{log_error_message, log_message};
 
 pub async fn detect_initial_power_state(manager: &Arc<Mutex<Manager>>) -> bool {
     let mgr = manager.lock().await;
@@ -17,6 +17,7 @@ pub async fn detect_initial_power_state(manager: &Arc<Mutex<Manager>>) -> bool {
     {
         let mut mgr = manager.lock().await;
         mgr.state.set_on_battery(!on_ac);
+        mgr.update_power_source().await;
     }
 
     log_message(&format!("Initial power deteciton: {}", if on_ac { "AC" } else { "Battery" }));
@@ -55,9 +56,28 @@ async fn is_on_ac_power() -> bool {
     false
 }
 
+/// Charge of the system batteries in percent, averaged when there are several.
+/// Peripheral batteries (`scope` Device: mice, headsets) are left out.
+fn battery_percent() -> Option<u8> {
+    let entries = fs::read_dir("/sys/class/power_supply/").ok()?;
+    let levels: Vec<u32> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|path| fs::read_to_string(path.join("type")).is_ok_and(|t| t.trim() == "Battery"))
+        .filter(|path| !fs::read_to_string(path.join("scope")).is_ok_and(|s| s.trim() == "Device"))
+        .filter_map(|path| fs::read_to_string(path.join("capacity")).ok()?.trim().parse().ok())
+        .collect();
+
+    if levels.is_empty() {
+        return None;
+    }
+    Some((levels.iter().sum::<u32>() / levels.len() as u32).min(100) as u8)
+}
+
+/// Watch for AC/battery changes and the battery's critical level. Expects
+/// `detect_initial_power_state` to have run.
 pub async fn spawn_power_source_monitor(manager: Arc<Mutex<Manager>>) {
-    let on_ac = detect_initial_power_state(&manager).await;
-    let mut last_on_ac = on_ac;
+    // Cleared when the critical action fires, set again once charged past rearm_percent
+    let mut critical_armed = true;
 
     let mut ticker = tokio::time::interval(Duration::from_secs(5));
     loop {
@@ -67,17 +87,39 @@ pub async fn spawn_power_source_monitor(manager: Arc<Mutex<Manager>>) {
         if !mgr.state.is_laptop() {
             continue;
         }
+        let critical = mgr.state.cfg.as_ref().and_then(|c| c.battery_critical.clone());
         drop(mgr); // release lock
 
+        // Every poll: a change pending on power_debounce_seconds is committed here
         let on_ac = is_on_ac_power().await;
-        if on_ac != last_on_ac {
-            last_on_ac = on_ac;
+        if manager.lock().await.observe_power_source(!on_ac).await {
             log_message(&format!("Power source changed: {}", if on_ac { "AC" } else { "Battery" }));
+        }
 
-            let mut mgr = manager.lock().await;
-            mgr.state.set_on_battery(!on_ac);
-            mgr.reset_instant_actions();
-            mgr.trigger_instant_actions().await;
+        // Power safety, independent of idle state, pauses and inhibitors
+        let (Some(critical), Some(percent)) = (critical, battery_percent()) else {
+            continue;
+        };
+        if critical_armed && !on_ac && percent < critical.percent {
+            critical_armed = false;
+            log_error_message(&format!(
+                "Warning: battery at {}% (below {}%), running critical action: {}",
+                percent, critical.percent, critical.command
+            ));
+            let opts = {
+                let mgr = manager.lock().await;
+                mgr.state.log_event(
+                    "battery_critical",
+                    serde_json::json!({ "percent": percent, "command": critical.command }),
+                );
+                mgr.state.hook_options()
+            };
+            if let Err(e) = run_command_detached_with(&critical.command, &opts).await {
+                log_error_message(&format!("Battery critical action failed: {}", e));
+            }
+        } else if !critical_armed && percent >= critical.rearm_percent {
+            critical_armed = true;
+            log_message(&format!("Battery at {}%, critical action re-armed", percent));
         }
     }
 }
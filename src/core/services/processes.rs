@@ -0,0 +1,75 @@
+use std::{fs, path::Path, sync::Arc, time::Duration};
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::{
+    core::manager::{helpers::wake_idle_tasks, InhibitReason, Manager},
+    log::log_message,
+};
+
+/// First running process whose name is in `names`. Compares `comm` (truncated
+/// to 15 characters by the kernel) and the basename of argv[0], so longer
+/// names match too.
+fn find_running_process(names: &[String]) -> Option<String> {
+    let entries = fs::read_dir("/proc").ok()?;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        if !file_name.to_string_lossy().bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        let dir = entry.path();
+
+        let comm = fs::read_to_string(dir.join("comm")).unwrap_or_default();
+        let comm = comm.trim();
+        if let Some(name) = names.iter().find(|n| n.as_str() == comm) {
+            return Some(name.clone());
+        }
+
+        let cmdline = fs::read(dir.join("cmdline")).unwrap_or_default();
+        let argv0 = cmdline.split(|&b| b == 0).next().unwrap_or_default();
+        let argv0 = String::from_utf8_lossy(argv0);
+        let base = Path::new(argv0.as_ref())
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if let Some(name) = names.iter().find(|n| **n == base) {
+            return Some(name.clone());
+        }
+    }
+    None
+}
+
+/// Inhibit idle while any of `names` is running (`inhibit_processes`). Unlike
+/// `inhibit_apps` this looks at processes, not windows, so it also works for
+/// background jobs and without compositor support.
+pub fn spawn_process_monitor(manager: Arc<Mutex<Manager>>, names: Vec<String>, interval: Duration) {
+    tokio::spawn(async move {
+        let shutdown = manager.lock().await.state.shutdown_flag.clone();
+        let mut active: Option<String> = None;
+
+        log_message(&format!(
+            "Process monitor started for [{}] (every {}s)",
+            names.join(", "),
+            interval.as_secs()
+        ));
+
+        loop {
+            let running = find_running_process(&names);
+            if running.is_some() != active.is_some() {
+                let mut mgr = manager.lock().await;
+                match &running {
+                    Some(name) => mgr.pause(InhibitReason::Process(name.clone())).await,
+                    None => {
+                        mgr.resume(InhibitReason::Process(String::new())).await;
+                        wake_idle_tasks(&mgr.state);
+                    }
+                }
+            }
+            active = running;
+
+            tokio::select! {
+                _ = sleep(interval) => {}
+                _ = shutdown.notified() => return,
+            }
+        }
+    });
+}
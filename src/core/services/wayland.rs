@@ -2,7 +2,7 @@ use eyre::Result;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::core::manager::Manager;
+use crate::core::manager::{InhibitReason, Manager};
 use crate::log::{log_error_message, log_message};
 
 use tokio::sync::Notify;
@@ -122,7 +122,7 @@ impl Dispatch<ExtIdleNotificationV1, ()> for WaylandIdleData {
                 }
                 IdleEvent::Resumed => {
                     log_message("Compositor detected activity");
-                    mgr.reset().await;
+                    mgr.register_activity().await;
                 }
                 _ => {}
             }
@@ -142,6 +142,13 @@ impl Dispatch<ZwpIdleInhibitorV1, ()> for WaylandIdleData {
     ) {
         state.active_inhibitors += 1;
         log_message(&format!("Inhibitor created, count={}", state.active_inhibitors));
+
+        if state.respect_inhibitors && state.active_inhibitors == 1 {
+            let manager = Arc::clone(&state.manager);
+            tokio::spawn(async move {
+                manager.lock().await.pause(InhibitReason::WaylandInhibitor).await;
+            });
+        }
     }
 }
 
@@ -157,6 +164,15 @@ impl Dispatch<ZwpIdleInhibitManagerV1, ()> for WaylandIdleData {
         if state.active_inhibitors > 0 {
             state.active_inhibitors -= 1;
             log_message(&format!("Inhibitor removed, count={}", state.active_inhibitors));
+
+            if state.respect_inhibitors && state.active_inhibitors == 0 {
+                let manager = Arc::clone(&state.manager);
+                tokio::spawn(async move {
+                    let mut mgr = manager.lock().await;
+                    mgr.resume(InhibitReason::WaylandInhibitor).await;
+                    mgr.state.notify.notify_one();
+                });
+            }
         }
     }
 }
@@ -1,4 +1,8 @@
-use std::{fs, time::Duration};
+use std::{env::var, fs, os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt}, path::{Path, PathBuf}, sync::OnceLock, time::{Duration, Instant}};
+
+use crate::log::{log_error_message, log_message};
+
+static RUNTIME_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 pub fn is_laptop() -> bool {
     let chassis_path = "/sys/class/dmi/id/chassis_type";
@@ -28,3 +32,161 @@ pub fn format_duration(dur: Duration) -> String {
         format!("{}h {}m", hours, minutes)
     }
 }
+
+/// Parse a duration such as "90", "45s", "500ms", "30m", "2h" or "1h30m" (bare
+/// numbers are seconds)
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    if let Ok(secs) = s.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let mut total_ms = 0u64;
+    let mut digits = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let value: u64 = digits.parse().ok()?;
+        digits.clear();
+        let unit_ms = match c {
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                1
+            }
+            's' => 1000,
+            'm' => 60_000,
+            'h' => 3_600_000,
+            'd' => 86_400_000,
+            _ => return None,
+        };
+        total_ms = total_ms.checked_add(value.checked_mul(unit_ms)?)?;
+    }
+
+    // Trailing digits without a unit ("1h30") are ambiguous
+    if !digits.is_empty() {
+        return None;
+    }
+    Some(Duration::from_millis(total_ms))
+}
+
+/// Compositor socket named by `WAYLAND_DISPLAY`: absolute, or relative to the
+/// runtime dir. None outside a Wayland session.
+pub fn wayland_socket_path() -> Option<PathBuf> {
+    let display = var("WAYLAND_DISPLAY").ok().filter(|d| !d.is_empty())?;
+    let path = PathBuf::from(display);
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        Some(runtime_dir().join(path))
+    }
+}
+
+/// True when a display server is reachable: the Wayland socket named by
+/// `WAYLAND_DISPLAY` exists, or X11's `DISPLAY` is set
+pub fn display_available() -> bool {
+    wayland_socket_path().is_some_and(|socket| socket.exists())
+        || var("DISPLAY").is_ok_and(|d| !d.is_empty())
+}
+
+/// `XDG_SESSION_TYPE` ("wayland", "x11", ...), lowercased
+pub fn session_type() -> Option<String> {
+    var("XDG_SESSION_TYPE")
+        .ok()
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+}
+
+/// Poll `display_available` until it succeeds or `timeout` elapses
+pub async fn wait_for_display(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if display_available() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Directory for runtime state (control socket, PID file, brightness and boot
+/// markers): `$XDG_RUNTIME_DIR`, else `/run/user/<uid>`, else `/tmp/stasis-<uid>`
+/// created with mode 0700. Chosen once per process; the choice is logged.
+pub fn runtime_dir() -> &'static Path {
+    RUNTIME_DIR.get_or_init(|| {
+        if let Ok(dir) = var("XDG_RUNTIME_DIR")
+            && !dir.is_empty()
+            && Path::new(&dir).is_dir()
+        {
+            return PathBuf::from(dir);
+        }
+
+        let uid = unsafe { libc::getuid() };
+        let user_run = PathBuf::from(format!("/run/user/{}", uid));
+        let dir = if owned_private_dir(&user_run, uid) {
+            user_run
+        } else {
+            let tmp = PathBuf::from(format!("/tmp/stasis-{}", uid));
+            if let Err(e) = ensure_private_dir(&tmp, uid) {
+                log_error_message(&format!("Failed to prepare runtime dir {}: {}", tmp.display(), e));
+            }
+            tmp
+        };
+        log_message(&format!("XDG_RUNTIME_DIR is not set, using {} for runtime files", dir.display()));
+        dir
+    })
+}
+
+/// An existing directory owned by `uid`
+fn owned_private_dir(path: &Path, uid: u32) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.is_dir() && m.uid() == uid)
+}
+
+/// Create `path` with mode 0700, or tighten an existing one we own. Refuses a
+/// directory owned by someone else (another user could have planted it in /tmp).
+fn ensure_private_dir(path: &Path, uid: u32) -> std::io::Result<()> {
+    match fs::DirBuilder::new().mode(0o700).create(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            let meta = fs::symlink_metadata(path)?;
+            if !meta.is_dir() || meta.uid() != uid {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "exists but is not a directory owned by this user",
+                ));
+            }
+            fs::set_permissions(path, fs::Permissions::from_mode(0o700))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Marker listing `once_per_boot` actions already fired this boot. Keyed on the
+/// kernel boot id, so a runtime dir that survives a reboot still starts fresh.
+fn boot_marker_path() -> Option<PathBuf> {
+    let boot_id = fs::read_to_string("/proc/sys/kernel/random/boot_id").ok()?;
+    Some(runtime_dir().join(format!("stasis-boot-{}", boot_id.trim())))
+}
+
+pub fn spent_this_boot(action_name: &str) -> bool {
+    boot_marker_path()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .is_some_and(|content| content.lines().any(|l| l == action_name))
+}
+
+pub fn mark_spent_this_boot(action_name: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let Some(path) = boot_marker_path() else {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "boot id unavailable"));
+    };
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", action_name)
+}
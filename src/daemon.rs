@@ -0,0 +1,126 @@
+use crate::core::utils::runtime_dir;
+use std::{
+    fs::{self, File},
+    io,
+    os::unix::io::AsRawFd,
+    path::PathBuf,
+    sync::OnceLock,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+/// Open PID file holding the single-instance lock; kept for the life of the process
+static INSTANCE_LOCK: OnceLock<File> = OnceLock::new();
+
+/// How long `--replace` waits for the old instance to exit
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// PID file of the running instance, in the runtime dir
+pub fn pid_file_path() -> PathBuf {
+    runtime_dir().join("stasis.pid")
+}
+
+pub fn write_pid_file() -> io::Result<()> {
+    fs::write(pid_file_path(), format!("{}\n", std::process::id()))
+}
+
+pub fn read_pid_file() -> Option<i32> {
+    fs::read_to_string(pid_file_path()).ok()?.trim().parse().ok()
+}
+
+/// Take an exclusive `flock` on the PID file. Returns `Ok(false)` if another
+/// instance holds it. The lock is released by the kernel when the process exits,
+/// so a stale PID file left by a crash never blocks startup.
+pub fn acquire_instance_lock() -> io::Result<bool> {
+    if INSTANCE_LOCK.get().is_some() {
+        return Ok(true);
+    }
+
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(pid_file_path())?;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == -1 {
+        let err = io::Error::last_os_error();
+        return if err.raw_os_error() == Some(libc::EWOULDBLOCK) { Ok(false) } else { Err(err) };
+    }
+
+    let _ = INSTANCE_LOCK.set(file);
+    Ok(true)
+}
+
+/// Ask the instance named in the PID file to shut down (SIGTERM) and wait until
+/// its lock is released
+pub fn replace_running_instance() -> io::Result<()> {
+    if acquire_instance_lock()? {
+        return Ok(());
+    }
+
+    if let Some(pid) = read_pid_file()
+        && unsafe { libc::kill(pid, libc::SIGTERM) } == -1
+    {
+        return Err(io::Error::last_os_error());
+    }
+
+    let deadline = Instant::now() + REPLACE_TIMEOUT;
+    while Instant::now() < deadline {
+        sleep(Duration::from_millis(100));
+        if acquire_instance_lock()? {
+            return Ok(());
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::TimedOut, "running instance did not exit"))
+}
+
+/// Remove the PID file, but only if it still belongs to this process
+pub fn remove_pid_file() {
+    let path = pid_file_path();
+    if let Ok(content) = fs::read_to_string(&path)
+        && content.trim() == std::process::id().to_string()
+    {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Detach from the terminal with the classic double fork: the first child calls
+/// `setsid` to drop the controlling TTY, the grandchild can never reacquire one.
+/// Only the grandchild returns; the original process and first child exit here.
+/// Must run before any threads (tokio runtime, D-Bus) are started.
+pub fn daemonize() -> io::Result<()> {
+    unsafe {
+        match libc::fork() {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            _ => libc::_exit(0),
+        }
+
+        if libc::setsid() == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        match libc::fork() {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            _ => libc::_exit(0),
+        }
+
+        libc::umask(0o022);
+    }
+
+    std::env::set_current_dir("/")?;
+
+    // Point stdio at /dev/null; logs still go to the log file
+    let dev_null = fs::OpenOptions::new().read(true).write(true).open("/dev/null")?;
+    let fd = std::os::unix::io::AsRawFd::as_raw_fd(&dev_null);
+    for target in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if unsafe { libc::dup2(fd, target) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    write_pid_file()
+}
@@ -1,19 +1,20 @@
 pub mod commands;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::UnixListener,
 };
 
 use crate::{
-    config, core::{
-        manager::{helpers::{get_manual_inhibit, set_manual_inhibit, trigger_all_idle_actions}, Manager}, 
+    config::{self, model::IdleAction}, core::{
+        manager::{helpers::{get_manual_inhibit, set_manual_inhibit, trigger_all_idle_actions}, panic, InhibitReason, Manager}, 
         services::app_inhibit::AppInhibitor,
+        utils::{format_duration, parse_duration},
     }, 
-    ipc::commands::trigger_action_by_name, 
+    ipc::commands::{test_action_by_name, trigger_action_by_name}, 
     log::{log_error_message, log_message}, 
-    SOCKET_PATH
+    socket_path
 };
 
 /// Spawn the IPC control socket task using a pre-bound listener.
@@ -34,11 +35,16 @@ pub async fn spawn_ipc_socket_with_listener(
 
                         let response = match cmd.as_str() {
                             // === CONFIG ===
+                            "reload" if cfg_path.is_empty() => {
+                                "ERROR: Config was not loaded from a file (stdin?), nothing to reload".to_string()
+                            }
+
                             "reload" => {
                                 match config::parser::load_config(&cfg_path) {
                                     Ok(new_cfg) => {
                                         let mut mgr = manager.lock().await;
                                         mgr.state.update_from_config(&new_cfg).await;
+                                        mgr.log_schedule();
                                         mgr.trigger_instant_actions().await;
 
                                         log_message("Config reloaded successfully");
@@ -54,16 +60,95 @@ pub async fn spawn_ipc_socket_with_listener(
                             // === PAUSE / RESUME ===
                             "pause" => {
                                 let mut mgr = manager.lock().await;
-                                mgr.pause(true).await;
+                                mgr.pause(InhibitReason::Manual).await;
                                 "Idle manager paused".to_string()
                             }
 
+                            cmd if cmd.starts_with("pause ") => {
+                                let arg = cmd.strip_prefix("pause ").unwrap_or("").trim();
+                                match parse_duration(arg) {
+                                    Some(duration) if !duration.is_zero() => {
+                                        let mut mgr = manager.lock().await;
+                                        mgr.pause_for(duration).await;
+                                        format!("Idle manager paused for {}", format_duration(duration))
+                                    }
+                                    _ => format!("ERROR: Invalid duration '{}' (e.g. 90, 30m, 1h30m)", arg),
+                                }
+                            }
+
+                            cmd if cmd.starts_with("snooze ") => {
+                                let arg = cmd.strip_prefix("snooze ").unwrap_or("").trim();
+                                match parse_duration(arg) {
+                                    Some(duration) if !duration.is_zero() => {
+                                        let mut mgr = manager.lock().await;
+                                        let ahead = mgr.snooze(duration);
+                                        match mgr.next_action_instant() {
+                                            Some(due) => format!(
+                                                "Idle actions snoozed for {}, next action in {}",
+                                                format_duration(ahead),
+                                                format_duration(due.saturating_duration_since(Instant::now()))
+                                            ),
+                                            None => format!("Idle actions snoozed for {}", format_duration(ahead)),
+                                        }
+                                    }
+                                    _ => format!("ERROR: Invalid duration '{}' (e.g. 90, 30m, 1h30m)", arg),
+                                }
+                            }
+
                             "resume" => {
                                 let mut mgr = manager.lock().await;
-                                mgr.resume(true).await;
+                                mgr.resume(InhibitReason::Manual).await;
                                 "Idle manager resumed".to_string()
                             }
 
+                            "reset-instants" | "reset_instants" => {
+                                let mut mgr = manager.lock().await;
+                                mgr.rerun_instant_actions().await;
+                                "Instant actions re-triggered".to_string()
+                            }
+
+                            "undo-all" | "undo_all" => {
+                                let mut mgr = manager.lock().await;
+                                mgr.undo_all().await;
+                                "Idle effects undone".to_string()
+                            }
+
+                            // === PROFILES ===
+                            cmd if cmd.starts_with("set-profile ") || cmd.starts_with("set_profile ") => {
+                                let name = cmd.split_once(' ').map(|(_, n)| n.trim()).unwrap_or("");
+                                let mut mgr = manager.lock().await;
+                                let target = match name {
+                                    "" | "none" | "default" => None,
+                                    name => Some(name),
+                                };
+                                match mgr.set_profile(target).await {
+                                    Ok(()) => match target {
+                                        Some(name) => format!("Profile '{}' active", name),
+                                        None => "Profile cleared".to_string(),
+                                    },
+                                    Err(e) => format!("ERROR: {e}"),
+                                }
+                            }
+
+                            // === PER-KIND INHIBIT ===
+                            cmd if cmd.starts_with("inhibit ") || cmd.starts_with("uninhibit ") => {
+                                let (verb, kind_name) = cmd.split_once(' ').unwrap_or((cmd, ""));
+                                match IdleAction::parse(kind_name.trim()) {
+                                    Some(kind) => {
+                                        let mut mgr = manager.lock().await;
+                                        if verb == "inhibit" {
+                                            mgr.inhibit_kind(kind.clone());
+                                            format!("Inhibiting {} actions", kind)
+                                        } else if mgr.uninhibit_kind(&kind) {
+                                            format!("No longer inhibiting {} actions", kind)
+                                        } else {
+                                            format!("{} actions were not inhibited", kind)
+                                        }
+                                    }
+                                    None => format!("ERROR: Unknown action kind '{}'", kind_name.trim()),
+                                }
+                            }
+
                             // === TRIGGER ===
                             cmd if cmd.starts_with("trigger ") => {
                                 let step = cmd.strip_prefix("trigger ").unwrap_or("").trim();
@@ -84,6 +169,70 @@ pub async fn spawn_ipc_socket_with_listener(
                                 }
                             }
 
+                            // === TEST-ACTION ===
+                            cmd if cmd.starts_with("test-action ") || cmd.starts_with("test_action ") => {
+                                let name = cmd.split_once(' ').map(|(_, n)| n).unwrap_or("").trim();
+                                if name.is_empty() {
+                                    "ERROR: No action name provided".to_string()
+                                } else {
+                                    match test_action_by_name(manager.clone(), name).await {
+                                        Ok(report) => report,
+                                        Err(e) => format!("ERROR: {e}"),
+                                    }
+                                }
+                            }
+
+                            // === COMPOSITOR-MANAGED ===
+                            cmd if cmd.starts_with("compositor-managed ") || cmd.starts_with("compositor_managed ") => {
+                                let arg = cmd.split_once(' ').map(|(_, a)| a.trim()).unwrap_or("");
+                                let managed = match arg {
+                                    "on" | "true" | "1" => Some(true),
+                                    "off" | "false" | "0" => Some(false),
+                                    _ => None,
+                                };
+                                match managed {
+                                    Some(managed) => {
+                                        let mut mgr = manager.lock().await;
+                                        let changed = mgr.set_compositor_managed(managed).await;
+                                        match (managed, changed) {
+                                            (true, true) => "Compositor-managed: timeout actions stand down".to_string(),
+                                            (false, true) => "Stasis manages idle again".to_string(),
+                                            (_, false) => format!("Compositor-managed was already {}", arg),
+                                        }
+                                    }
+                                    None => format!("ERROR: Expected 'on' or 'off', got '{}'", arg),
+                                }
+                            }
+
+                            // === PING ===
+                            // Answered without the manager lock, so it still works
+                            // (and shows a stale tick) when the idle loop is stuck
+                            "ping" => {
+                                let uptime = crate::core::manager::uptime();
+                                match crate::core::manager::last_idle_tick() {
+                                    Some(tick) => {
+                                        let age = tick.elapsed().unwrap_or_default();
+                                        let unix = tick.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+                                        format!(
+                                            "pong uptime={}s last_tick={} last_tick_age={}s",
+                                            uptime.as_secs(), unix.as_secs(), age.as_secs()
+                                        )
+                                    }
+                                    None => format!("pong uptime={}s last_tick=none", uptime.as_secs()),
+                                }
+                            }
+
+                            // === PANIC ===
+                            "panic" | "lock-suspend-now" => {
+                                match panic(&manager).await {
+                                    Ok(()) => "Locked and suspending".to_string(),
+                                    Err(e) => {
+                                        log_error_message(&format!("Panic failed: {e}"));
+                                        format!("ERROR: {e}")
+                                    }
+                                }
+                            }
+
                             // === STOP ===
                             "stop" => {
                                 log_message("Received stop command — shutting down gracefully");
@@ -92,7 +241,8 @@ pub async fn spawn_ipc_socket_with_listener(
                                     let mut mgr = manager_clone.lock().await;
                                     mgr.shutdown().await;
                                     log_message("Manager shutdown complete, exiting process");
-                                    let _ = std::fs::remove_file(SOCKET_PATH);
+                                    let _ = std::fs::remove_file(socket_path());
+                                    crate::daemon::remove_pid_file();
                                     std::process::exit(0);
                                 });
                                 "Stopping Stasis...".to_string()
@@ -139,27 +289,50 @@ pub async fn spawn_ipc_socket_with_listener(
                                 let idle_time = mgr.state.last_activity_display.elapsed();
                                 let uptime = mgr.state.start_time.elapsed();
                                 let mut inhibitor = app_inhibitor.lock().await;
-                                let app_blocking = inhibitor.is_any_app_running().await;
+                                let app_blocking = inhibitor.is_any_app_running().await || inhibitor.is_fullscreen();
                                 let idle_inhibited = mgr.state.manually_paused || mgr.state.paused || app_blocking;
+                                let reason = mgr.inhibit_reason();
+                                let remaining = mgr.manual_pause_remaining();
+                                let progress = mgr.idle_progress();
+                                let idle_for = mgr.idle_since().map(|t| t.elapsed());
+                                let next_action = mgr.next_action_instant().map(|t| t.saturating_duration_since(Instant::now()));
+                                let snoozed = mgr.snooze_remaining();
+                                let remaining_text = match (&reason, remaining) {
+                                    (Some(InhibitReason::Manual), Some(d)) => format!("{} remaining", format_duration(d)),
+                                    (Some(InhibitReason::Manual), None) => "indefinite".to_string(),
+                                    _ => "-".to_string(),
+                                };
 
                                 if as_json {
                                     // Build JSON output and return as string
                                     serde_json::json!({
                                         "text": if idle_inhibited { "☕" } else { "⌚" },
                                         "tooltip": format!(
-                                            "{}\nIdle time: {}s\nUptime: {}s\nPaused: {}\nManually paused: {}\nApp blocking: {}",
+                                            "{}\nIdle time: {}s\nUptime: {}s\nPaused: {}\nManually paused: {}\nApp blocking: {}\nInhibit reason: {}\nInhibit remaining: {}",
                                             if idle_inhibited { "Idle inhibited" } else { "Idle active" },
                                             idle_time.as_secs(),
                                             uptime.as_secs(),
                                             mgr.state.paused,
                                             mgr.state.manually_paused,
-                                            app_blocking
-                                        )
+                                            app_blocking,
+                                            reason.as_ref().map(|r| r.to_string()).unwrap_or_else(|| "-".to_string()),
+                                            remaining_text
+                                        ),
+                                        "inhibit_reason": reason.as_ref().map(|r| r.to_string()),
+                                        "profile": mgr.state.profile,
+                                        "idle": mgr.is_idle(),
+                                        "idle_since_seconds": idle_for.map(|d| d.as_secs()),
+                                        "inhibit_remaining_seconds": remaining.map(|d| d.as_secs()),
+                                        "next_action_seconds": next_action.map(|d| d.as_secs()),
+                                        "snooze_remaining_seconds": snoozed.map(|d| d.as_secs()),
+                                        "idle_progress": progress,
+                                        // Waybar renders this as {percentage} / format-icons
+                                        "percentage": progress.map(|p| (p * 100.0).round() as u8)
                                     })
                                     .to_string()
                                 } else if let Some(cfg) = &mgr.state.cfg {
                                     // Dereference Arc to call pretty_print
-                                    cfg.pretty_print(Some(idle_time), Some(uptime), Some(idle_inhibited))
+                                    cfg.pretty_print(Some(idle_time), Some(uptime), Some(idle_inhibited), reason.as_ref(), remaining, idle_for, next_action, snoozed, Some(&mgr.state.metrics))
                                 } else {
                                     "No configuration loaded".to_string()
                                 }
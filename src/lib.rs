@@ -0,0 +1,329 @@
+pub mod cli;
+pub mod config;
+pub mod core;
+pub mod daemon;
+pub mod ipc;
+pub mod log;
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use eyre::Result;
+use tokio::{net::UnixListener, sync::Mutex};
+
+use crate::{
+    config::model::StasisConfig,
+    core::{
+        manager::{helpers::{restore_crashed_brightness, set_brightness_backend}, finish_suspend_after_lock, spawn_idle_task, spawn_lock_watcher, Manager}, 
+        services::{
+            app_inhibit::{AppInhibitor, spawn_app_inhibit_task},
+            dbus::listen_for_power_events, 
+            input::spawn_input_task,
+            load::spawn_load_monitor,
+            media::spawn_media_monitor_dbus,
+            metrics::spawn_metrics_server,
+            network::spawn_network_monitor,
+            power_detection::{detect_initial_power_state, spawn_power_source_monitor},
+            processes::spawn_process_monitor,
+            wayland::{setup as setup_wayland},
+        },
+        utils::{runtime_dir, wait_for_display, wayland_socket_path},
+    },
+    log::{log_error_message, log_message},
+};
+
+/// Control socket shared by the daemon and the CLI client, in the runtime dir
+pub fn socket_path() -> PathBuf {
+    runtime_dir().join("stasis.sock")
+}
+
+/// How long startup waits for the Wayland socket before going on without it
+const DISPLAY_WAIT_SECS: u64 = 30;
+
+/// Process-level integrations `run` sets up in addition to the idle machinery.
+/// The defaults suit embedding: nothing that touches signals, exits the process
+/// or claims the control socket.
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    /// Config file re-read by the IPC `reload` command
+    pub config_path: Option<PathBuf>,
+    /// Pre-bound control socket to serve IPC commands on
+    pub control_socket: Option<UnixListener>,
+    /// Shut down and exit the process on SIGINT/SIGTERM/SIGHUP
+    pub handle_signals: bool,
+    /// Shut down and exit the process when the compositor's socket disappears
+    pub exit_with_compositor: bool,
+    /// Debug only: replace suspend commands with `rtcwake -m mem -s <secs>` so
+    /// pre-suspend and resume hooks can be tested without a long sleep
+    pub soft_suspend_secs: Option<u64>,
+}
+
+/// Handle to a running stasis instance returned by [`run`]
+#[derive(Clone)]
+pub struct StasisHandle {
+    manager: Arc<Mutex<Manager>>,
+    app_inhibitor: Arc<Mutex<AppInhibitor>>,
+}
+
+impl StasisHandle {
+    pub fn manager(&self) -> Arc<Mutex<Manager>> {
+        Arc::clone(&self.manager)
+    }
+
+    /// Stop all background tasks; the handle is unusable afterwards
+    pub async fn shutdown(&self) {
+        self.manager.lock().await.shutdown().await;
+        self.app_inhibitor.lock().await.shutdown().await;
+    }
+}
+
+/// One-shot mode (`--once`): pick the block for the current power source, run
+/// `action` by name (or every instant action of the block when None) and return.
+/// No idle loop, watchers or control socket are started.
+pub async fn run_once(config: StasisConfig, action: Option<&str>) -> Result<String> {
+
+    let mut manager = Manager::new(Arc::new(config));
+    set_brightness_backend(&mut manager.state).await;
+    let manager = Arc::new(Mutex::new(manager));
+    detect_initial_power_state(&manager).await;
+
+    let mut mgr = manager.lock().await;
+    match action {
+        Some(name) => {
+            let fired = mgr.trigger_action_by_name(name).await.map_err(|e| eyre::eyre!(e))?;
+            drop(mgr);
+            // No lock watcher here to run a suspend waiting on the lock screen
+            finish_suspend_after_lock(&manager).await;
+            Ok(fired)
+        }
+        None => {
+            if mgr.state.instant_actions.is_empty() {
+                return Err(eyre::eyre!("No instant (timeout 0) actions in the config"));
+            }
+            mgr.trigger_instant_actions().await;
+            Ok("instant actions".to_string())
+        }
+    }
+}
+
+/// Wire up the manager, idle loop, lock watcher, input, media, power and suspend
+/// listeners on the current tokio runtime and return once everything is running.
+/// Must be called from within a `LocalSet`-free tokio context (tasks are `tokio::spawn`ed).
+pub async fn run(config: StasisConfig, opts: RunOptions) -> Result<StasisHandle> {
+    let cfg = Arc::new(config);
+
+    // --- Wait for the compositor when started early (e.g. systemd ordering) ---
+    if !wait_for_display(Duration::from_secs(DISPLAY_WAIT_SECS)).await {
+        log_error_message(&format!(
+            "No display available after {}s; GUI actions will be skipped until one appears",
+            DISPLAY_WAIT_SECS
+        ));
+    }
+
+    let mut manager = Manager::new(Arc::clone(&cfg));
+    set_brightness_backend(&mut manager.state).await;
+
+    // --- Undo a dim left behind by a killed instance ---
+    if cfg.manage_brightness {
+        restore_crashed_brightness(&mut manager.state).await;
+    }
+    if let Some(secs) = opts.soft_suspend_secs {
+        log_message(&format!(
+            "Debug soft-suspend enabled: suspend actions will run 'rtcwake -m mem -s {}'",
+            secs
+        ));
+        manager.state.soft_suspend_secs = Some(secs);
+    }
+    let manager = Arc::new(Mutex::new(manager));
+
+    // Pick the AC/battery block before any instant action fires
+    detect_initial_power_state(&manager).await;
+    {
+        // Laptops already logged it when the AC/battery block was picked
+        let mgr = manager.lock().await;
+        if mgr.state.current_block.is_none() {
+            mgr.log_schedule();
+        }
+    }
+
+    // Immediately trigger instants at startup, unless they should wait for the first idle cycle
+    if cfg.trigger_instant_on_startup {
+        let mut mgr = manager.lock().await;
+        mgr.trigger_instant_actions().await;
+    } else {
+        log_message("Instant actions armed; they will fire on the first idle cycle");
+    }
+    
+    // --- Spawn background tasks ---
+    let idle_handle = spawn_idle_task(Arc::clone(&manager));
+    let lock_handle = spawn_lock_watcher(Arc::clone(&manager)).await;
+    let input_handle = spawn_input_task(Arc::clone(&manager));
+    
+    // Store handles in manager
+    {
+        let mut mgr = manager.lock().await;
+        mgr.idle_task_handle = Some(idle_handle);
+        mgr.lock_task_handle = Some(lock_handle);
+        mgr.input_task_handle = Some(input_handle);
+    } 
+    
+    // --- Spawn suspend event listener ---
+    let dbus_manager = Arc::clone(&manager);
+    tokio::spawn(async move {
+        if let Err(e) = listen_for_power_events(dbus_manager).await {
+            log_error_message(&format!("D-Bus suspend event listener failed: {}", e));
+        }
+    });
+    
+    // --- AC/Battery Detection ---
+    let laptop_manager = Arc::clone(&manager);
+    tokio::spawn(spawn_power_source_monitor(laptop_manager));
+
+   // --- Spawn app inhibit task ---
+    let app_inhibitor = spawn_app_inhibit_task(
+        Arc::clone(&manager),
+        Arc::clone(&cfg)
+    ).await;
+   
+    // --- Spawn media monitor task ---
+    if cfg.monitor_media {
+        if let Err(e) = spawn_media_monitor_dbus(
+            Arc::clone(&manager),
+            cfg.ignore_remote_media,
+            cfg.ignored_players.clone(),
+        ).await {
+            log_error_message(&format!("Failed to spawn media monitor: {}", e));
+        }
+    }
+    
+    // --- Network traffic as activity (headless setups) ---
+    if cfg.network_activity_inhibit {
+        spawn_network_monitor(Arc::clone(&manager), cfg.network_activity_threshold_kbps);
+    }
+
+    // --- Load average inhibition ---
+    if let Some(threshold) = cfg.inhibit_above_load {
+        spawn_load_monitor(Arc::clone(&manager), threshold);
+    }
+
+    // --- Process-based inhibition ---
+    if !cfg.inhibit_processes.is_empty() {
+        spawn_process_monitor(
+            Arc::clone(&manager),
+            cfg.inhibit_processes.clone(),
+            Duration::from_secs(cfg.inhibit_process_interval),
+        );
+    }
+
+    // --- Metrics endpoint (off unless metrics_listen is set) ---
+    if let Some(addr) = &cfg.metrics_listen {
+        spawn_metrics_server(Arc::clone(&manager), addr.clone()).await;
+    }
+
+    // --- Wayland setup ---
+    let wayland_manager = Arc::clone(&manager);
+    let _ = setup_wayland(wayland_manager, cfg.respect_wayland_inhibitors).await?;
+
+    // -- IPC Control Socket ---
+    if let Some(listener) = opts.control_socket {
+        let cfg_path = opts
+            .config_path
+            .as_ref()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        ipc::spawn_ipc_socket_with_listener(
+            Arc::clone(&manager),
+            Arc::clone(&app_inhibitor),
+            cfg_path,
+            listener,
+        ).await;
+    }
+
+    if opts.handle_signals {
+        setup_shutdown_handler(
+            Arc::clone(&manager),
+            Arc::clone(&app_inhibitor),
+        ).await;
+    }
+
+    // Monitor Wayland compositor connection
+    if opts.exit_with_compositor {
+        spawn_wayland_monitor(
+            Arc::clone(&manager),
+            Arc::clone(&app_inhibitor),
+        ).await;
+    }
+    
+    // --- Log startup message ---
+    log_message(&format!("Running. Idle actions loaded: {}", cfg.actions.len()));
+
+    Ok(StasisHandle { manager, app_inhibitor })
+}
+
+/// Async shutdown handler (Ctrl+C / SIGTERM)
+async fn setup_shutdown_handler(
+    idle_timer: Arc<Mutex<Manager>>,
+    app_inhibitor: Arc<Mutex<AppInhibitor>>,
+) {
+    let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt()).unwrap();
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).unwrap();
+    let mut sighup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).unwrap();
+
+    tokio::spawn({
+        let manager = Arc::clone(&idle_timer);
+        let app_inhibitor = Arc::clone(&app_inhibitor);
+        async move {
+            tokio::select! {
+                _ = sigint.recv() => {
+                    log_message("Received SIGINT, shutting down...");
+                },
+                _ = sigterm.recv() => {
+                    log_message("Received SIGTERM, shutting down...");
+                },
+                _ = sighup.recv() => {
+                    log_message("Received SIGHUP, shutting down...");
+                },
+            }
+
+            // Shutdown idle timer
+            manager.lock().await.shutdown().await;
+
+            // Shutdown app inhibitor
+            app_inhibitor.lock().await.shutdown().await;
+
+            let _ = std::fs::remove_file(socket_path());
+            daemon::remove_pid_file();
+            log_message("Shutdown complete, goodbye!");
+            std::process::exit(0);
+        }
+    });
+}
+
+async fn spawn_wayland_monitor(
+    manager: Arc<Mutex<Manager>>,
+    app_inhibitor: Arc<Mutex<AppInhibitor>>,
+) {
+    tokio::spawn(async move {
+        let Some(compositor_socket) = wayland_socket_path() else {
+            return;
+        };
+
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+            
+            // Check if Wayland socket still exists
+            if !compositor_socket.exists() {
+                log_message("Wayland compositor socket disappeared, shutting down...");
+                
+                // Shutdown idle timer
+                manager.lock().await.shutdown().await;
+                
+                // Shutdown app inhibitor
+                app_inhibitor.lock().await.shutdown().await;
+                
+                let _ = std::fs::remove_file(socket_path());
+                daemon::remove_pid_file();
+                log_message("Shutdown complete, goodbye!");
+                std::process::exit(0);
+            }
+        }
+    });
+}
@@ -1,6 +1,6 @@
 use std::fs::{OpenOptions, create_dir_all, metadata, remove_file};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use chrono::Local;
 use once_cell::sync::Lazy;
 use std::sync::{Mutex, Once};
@@ -8,14 +8,57 @@ use std::sync::{Mutex, Once};
 /// Maximum log file size in bytes before rotation (50 MB)
 const MAX_LOG_SIZE: u64 = 50 * 1024 * 1024;
 
+/// Environment variable consulted for the log level when no `-v` flag is given
+pub const LOG_ENV_VAR: &str = "STASIS_LOG";
+
+/// Log verbosity, ordered from least to most verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            "trace" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+
+    /// Map the number of `-v` flags to a level (`-v` = debug, `-vv` = trace)
+    pub fn from_verbosity(count: u8) -> Option<Self> {
+        match count {
+            0 => None,
+            1 => Some(LogLevel::Debug),
+            _ => Some(LogLevel::Trace),
+        }
+    }
+}
+
+impl std::fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::Info => write!(f, "info"),
+            LogLevel::Debug => write!(f, "debug"),
+            LogLevel::Trace => write!(f, "trace"),
+        }
+    }
+}
+
 /// Global runtime config
 pub struct Config {
     pub verbose: bool,
+    pub level: LogLevel,
 }
 
 pub static GLOBAL_CONFIG: Lazy<Mutex<Config>> = Lazy::new(|| {
     Mutex::new(Config {
         verbose: false, // default
+        level: LogLevel::Info,
     })
 });
 
@@ -27,6 +70,20 @@ pub fn set_verbose(enabled: bool) {
     config.verbose = enabled;
 }
 
+pub fn set_log_level(level: LogLevel) {
+    let mut config = GLOBAL_CONFIG.lock().unwrap();
+    config.level = level;
+}
+
+pub fn log_level() -> LogLevel {
+    GLOBAL_CONFIG.lock().unwrap().level
+}
+
+/// Log level requested through `STASIS_LOG`, if set to a known value
+pub fn env_log_level() -> Option<LogLevel> {
+    std::env::var(LOG_ENV_VAR).ok().and_then(|v| LogLevel::parse(&v))
+}
+
 /// Get log file path
 fn log_path() -> PathBuf {
     let mut path = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
@@ -94,3 +151,41 @@ pub fn log_error_message(message: &str) {
     }
 }
 
+pub fn log_debug_message(message: &str) {
+    if log_level() >= LogLevel::Debug {
+        log_message(&format!("[DEBUG] {}", message));
+    }
+}
+
+pub fn log_trace_message(message: &str) {
+    if log_level() >= LogLevel::Trace {
+        log_message(&format!("[TRACE] {}", message));
+    }
+}
+
+/// Append one record to the event log at `path` (`event_log`): `fields` (a JSON
+/// object) plus `ts` (local time, RFC 3339), `event` and the active `profile`
+pub fn log_event(path: &Path, event: &str, profile: Option<&str>, fields: serde_json::Value) {
+    let mut record = serde_json::json!({
+        "ts": Local::now().to_rfc3339(),
+        "event": event,
+        "profile": profile,
+    });
+    if let (Some(record), serde_json::Value::Object(fields)) = (record.as_object_mut(), fields) {
+        record.extend(fields);
+    }
+
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        let _ = create_dir_all(dir);
+    }
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", record));
+    if let Err(e) = written {
+        log_error_message(&format!("Failed to write event log {}: {}", path.display(), e));
+    }
+}
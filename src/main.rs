@@ -1,242 +1,265 @@
-pub mod cli;
-pub mod config;
-pub mod core;
-pub mod ipc;
-pub mod log;
-
-use std::{env::var, fs, process::exit, sync::Arc};
+use std::{env::var, fs, path::PathBuf, process::exit};
 use clap::Parser;
 use eyre::Result;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::{UnixListener, UnixStream}, 
-    sync::Mutex, 
     task::LocalSet
 };
 
-use crate::{
-    cli::Command,
-    config::parser::load_config,
-    core::{
-        manager::{spawn_idle_task, spawn_lock_watcher, Manager}, 
-        services::{
-            app_inhibit::{AppInhibitor, spawn_app_inhibit_task},
-            dbus::listen_for_power_events, 
-            input::spawn_input_task,
-            media::spawn_media_monitor_dbus,
-            power_detection::spawn_power_source_monitor,
-            wayland::{setup as setup_wayland},
-        }
-    },
+use stasis::{
+    cli::{Args, Command},
+    config::{model::StasisConfig, find_config_path, get_config_path, template::default_config_template, user_config_path, parser::{load_config_from_str, load_config_report}},
+    daemon,
+    log::{env_log_level, log_error_message, log_message, set_log_level, set_verbose, LogLevel},
+    run, run_once, socket_path, RunOptions,
 };
 
-use crate::{
-    cli::Args, 
-    config::get_config_path, 
-    log::{log_error_message, log_message, set_verbose}
-};
+fn main() -> Result<()> {
+    let mut args = Args::parse();
 
-const SOCKET_PATH: &str = "/tmp/stasis.sock";
+    if args.print_default_config {
+        print!("{}", default_config_template());
+        return Ok(());
+    }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-    
-    if var("WAYLAND_DISPLAY").is_err() {
-        eprintln!("Warn: Stasis requires wayland to run.");
-        exit(1);
+    if args.print_config_path {
+        match args.config.clone().or_else(find_config_path) {
+            Some(path) => println!("{}", path.display()),
+            None => {
+                let expected = user_config_path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "~/.config/stasis/stasis.rune".to_string());
+                eprintln!("No config file found; create one at {}", expected);
+                exit(1);
+            }
+        }
+        return Ok(());
     }
 
-    // --- Handle subcommands via socket ---
-    if let Some(cmd) = &args.command {
-        use tokio::net::UnixStream;
+    // `--once` needs no compositor, so it runs from a TTY or under X11 as well
+    if args.once.is_some() {
+        return tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(run_once_cli(args));
+    }
 
-        match cmd {
-            Command::Info { json } => {
-                if let Ok(mut stream) = UnixStream::connect(SOCKET_PATH).await {
-                    let msg = if *json { "info --json" } else { "info" };
-                    let _ = stream.write_all(msg.as_bytes()).await;
+    // Not fatal: the daemon waits for a display before running GUI actions
+    if args.command.is_none() && var("WAYLAND_DISPLAY").is_err() && var("DISPLAY").is_err() {
+        eprintln!("Warn: no WAYLAND_DISPLAY or DISPLAY set; lock, DPMS and brightness actions will be skipped");
+    }
 
-                    let mut response = Vec::new();
-                    let _ = stream.read_to_end(&mut response).await;
-                    println!("{}", String::from_utf8_lossy(&response));
-                } else {
-                    if *json {
-                        println!(r#"{{"text":"😴","tooltip":"No running Stasis instance found"}}"#);
-                    } else {
-                        eprintln!("No running Stasis instance found");
-                        std::process::exit(1);
-                    }
+    if args.command.is_none() {
+        // --- Single instance guard (flock on the PID file) ---
+        if args.replace {
+            if let Err(e) = daemon::replace_running_instance() {
+                eprintln!("Failed to replace running instance: {}", e);
+                exit(1);
+            }
+        } else {
+            match daemon::acquire_instance_lock() {
+                Ok(true) => {}
+                Ok(false) => {
+                    eprintln!("Another instance of Stasis is already running");
+                    log_error_message("Another instance is already running.");
+                    exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to lock PID file {}: {}", daemon::pid_file_path().display(), e);
+                    exit(1);
                 }
             }
-            Command::Trigger { step } => {
-                if let Ok(mut stream) = UnixStream::connect(SOCKET_PATH).await {
-                    let msg = format!("trigger {}", step);
-                    let _ = stream.write_all(msg.as_bytes()).await;
+        }
 
-                    let mut response = Vec::new();
-                    let _ = stream.read_to_end(&mut response).await;
-                    let response_text = String::from_utf8_lossy(&response);
-                    
-                    if response_text.starts_with("ERROR:") {
-                        eprintln!("{}", response_text.trim_start_matches("ERROR:").trim());
-                        std::process::exit(1);
-                    } else if !response_text.is_empty() {
-                        println!("{}", response_text);
-                    } else {
-                        println!("Action '{}' triggered", step);
-                    }
-                } else {
-                    eprintln!("No running Stasis instance found");
-                    std::process::exit(1);
-                }
+        // Fork before the tokio runtime (and with it D-Bus, libinput...) starts, so
+        // all of it lives in the daemonized child
+        if args.daemon {
+            // stdin is pointed at /dev/null once detached
+            if args.config.as_ref().is_some_and(|p| p.as_os_str() == "-") {
+                eprintln!("--config - (stdin) cannot be combined with --daemon");
+                exit(1);
             }
-            Command::ListActions => {
-                if let Ok(mut stream) = UnixStream::connect(SOCKET_PATH).await {
-                    let _ = stream.write_all(b"list_actions").await;
 
-                    let mut response = Vec::new();
-                    let _ = stream.read_to_end(&mut response).await;
-                    println!("{}", String::from_utf8_lossy(&response));
-                } else {
-                    eprintln!("No running Stasis instance found");
-                    std::process::exit(1);
-                }
+            // The daemon changes directory to /, so resolve a relative config path first
+            if let Some(path) = &args.config
+                && path.as_os_str() != "-"
+            {
+                args.config = Some(fs::canonicalize(path)?);
             }
-            _ => {
-                let msg = match cmd {
-                    Command::Reload => "reload",
-                    Command::Pause => "pause",
-                    Command::Resume => "resume",
-                    Command::ToggleInhibit => "toggle_inhibit",
-                    Command::Stop => "stop",
-                    _ => unreachable!(),
-                };
 
-                if let Ok(mut stream) = UnixStream::connect(SOCKET_PATH).await {
-                    let _ = stream.write_all(msg.as_bytes()).await;
+            daemon::daemonize().map_err(|e| eyre::eyre!("Failed to daemonize: {}", e))?;
+        } else {
+            daemon::write_pid_file()?;
+        }
+    }
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(run_cli(args))
+}
 
-                    // Only read response for commands that send one back
-                    if msg == "toggle_inhibit" {
-                        let mut response = Vec::new();
-                        let _ = stream.read_to_end(&mut response).await;
-                        println!("{}", String::from_utf8_lossy(&response));
-                    } else {
-                        // Success message for other commands
-                        let success_msg = match cmd {
-                            Command::Reload => "Configuration reloaded successfully",
-                            Command::Pause => "Idle timers paused",
-                            Command::Resume => "Idle timers resumed",
-                            Command::Stop => "Stasis daemon stopped",
-                            _ => "",
-                        };
-                        if !success_msg.is_empty() {
-                            println!("{}", success_msg);
-                        }
-                    }
-                } else {
-                    eprintln!("No running Stasis instance found");
-                    std::process::exit(1);
-                }
-            }
+/// Resolve the config path (`--config`, else the default lookup) and load it;
+/// `-` reads the config from stdin
+async fn load_cli_config(config: Option<PathBuf>) -> Result<(PathBuf, StasisConfig)> {
+    let config_path = match config {
+        Some(path) => path,
+        None => get_config_path().await?,
+    };
+
+    let cfg = if config_path.as_os_str() == "-" {
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut content)?;
+        load_config_from_str(&content).map_err(eyre::Report::new)?
+    } else {
+        load_config_report(config_path.to_str().unwrap())?
+    };
+    Ok((config_path, cfg))
+}
+
+/// `--once`: load the config, run the requested action(s) and exit
+async fn run_once_cli(args: Args) -> Result<()> {
+    if let Some(level) = LogLevel::from_verbosity(args.verbose).or_else(env_log_level) {
+        set_verbose(true);
+        set_log_level(level);
+    }
+    let (_, cfg) = load_cli_config(args.config).await?;
+
+    let action = args.once.as_deref().filter(|name| !name.is_empty());
+    match run_once(cfg, action).await {
+        Ok(fired) => {
+            println!("Ran {}", fired);
+            Ok(())
         }
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+/// What the client does with the daemon's reply
+enum Reply {
+    /// Print it as is
+    Print,
+    /// Exit 1 with the message if it starts with `ERROR:`, else print it (or
+    /// the fallback when it is empty)
+    Checked(Option<String>),
+    /// Print this without waiting for a reply
+    Fixed(&'static str),
+}
 
+/// IPC message for a subcommand and how to present the reply
+fn ipc_request(cmd: &Command) -> (String, Reply) {
+    match cmd {
+        Command::Info { json: true } => ("info --json".into(), Reply::Print),
+        Command::Info { json: false } => ("info".into(), Reply::Print),
+        Command::ListActions => ("list_actions".into(), Reply::Print),
+        Command::Ping => ("ping".into(), Reply::Print),
+        Command::ToggleInhibit => ("toggle_inhibit".into(), Reply::Print),
+        Command::Trigger { step } => (
+            format!("trigger {}", step),
+            Reply::Checked(Some(format!("Action '{}' triggered", step))),
+        ),
+        Command::TestAction { name } => (format!("test-action {}", name), Reply::Checked(None)),
+        Command::Inhibit { kind } => (format!("inhibit {}", kind), Reply::Checked(None)),
+        Command::Uninhibit { kind } => (format!("uninhibit {}", kind), Reply::Checked(None)),
+        Command::CompositorManaged { state } => (format!("compositor-managed {}", state), Reply::Checked(None)),
+        Command::SetProfile { name } => (format!("set-profile {}", name), Reply::Checked(None)),
+        Command::Pause { duration: Some(duration) } => (format!("pause {}", duration), Reply::Checked(None)),
+        Command::Snooze { duration } => (format!("snooze {}", duration), Reply::Checked(None)),
+        Command::Panic => ("panic".into(), Reply::Checked(None)),
+        Command::Reload => ("reload".into(), Reply::Checked(None)),
+        Command::Pause { duration: None } => ("pause".into(), Reply::Fixed("Idle timers paused")),
+        Command::Resume => ("resume".into(), Reply::Fixed("Idle timers resumed")),
+        Command::Stop => ("stop".into(), Reply::Fixed("Stasis daemon stopped")),
+        Command::UndoAll => ("undo-all".into(), Reply::Fixed("Idle effects undone")),
+        Command::ResetInstants => ("reset-instants".into(), Reply::Fixed("Instant actions re-triggered")),
+    }
+}
+
+/// Send a subcommand to the running daemon over the control socket and print the result
+async fn run_client(cmd: &Command) -> Result<()> {
+    let Ok(mut stream) = UnixStream::connect(socket_path()).await else {
+        // Waybar polls `info --json`; give it something to render
+        if matches!(cmd, Command::Info { json: true }) {
+            println!(r#"{{"text":"😴","tooltip":"No running Stasis instance found"}}"#);
+            return Ok(());
+        }
+        eprintln!("No running Stasis instance found");
+        exit(1);
+    };
+
+    let (msg, reply) = ipc_request(cmd);
+    let _ = stream.write_all(msg.as_bytes()).await;
+
+    if let Reply::Fixed(text) = reply {
+        println!("{}", text);
         return Ok(());
     }
+
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response).await;
+    let response_text = String::from_utf8_lossy(&response);
+
+    match reply {
+        Reply::Checked(_) if response_text.starts_with("ERROR:") => {
+            eprintln!("{}", response_text.trim_start_matches("ERROR:").trim());
+            exit(1);
+        }
+        Reply::Checked(Some(fallback)) if response_text.is_empty() => println!("{}", fallback),
+        _ => println!("{}", response_text),
+    }
+    Ok(())
+}
+
+async fn run_cli(args: Args) -> Result<()> {
+    // --- Client mode: forward the subcommand to the running daemon ---
+    if let Some(cmd) = &args.command {
+        return run_client(cmd).await;
+    }
     
     // --- Single Instance enforcement ---
     let just_help_or_version = std::env::args().any(|a| matches!(a.as_str(), "-V" | "--version" | "-h" | "--help" | "help"));
-    if let Ok(_) = UnixStream::connect(SOCKET_PATH).await {
+    if let Ok(_) = UnixStream::connect(socket_path()).await {
         if !just_help_or_version {
             eprintln!("Another instance of Stasis is already running");
         }
         log_error_message("Another instance is already running.");
-        return Ok(());
+        exit(1);
     }
-    let _ = fs::remove_file(SOCKET_PATH);
-    let listener = UnixListener::bind(SOCKET_PATH).map_err(|_| {
+    let _ = fs::remove_file(socket_path());
+    let listener = UnixListener::bind(socket_path()).map_err(|_| {
         eyre::eyre!("Failed to bind control socket. Another instance may be running.")
     })?;
     
     // --- Load config ---
-    let config_path = args.config.unwrap_or(get_config_path().await?);
-    if args.verbose {
+    let cli_level = LogLevel::from_verbosity(args.verbose);
+    if cli_level.is_some() {
         log_message("Verbose mode enabled");
         set_verbose(true);
     }
-    let cfg = Arc::new(load_config(config_path.to_str().unwrap())?);
-    let manager = Manager::new(Arc::clone(&cfg));
-    let manager = Arc::new(Mutex::new(manager));
+    let (config_path, cfg) = load_cli_config(args.config).await?;
+    // `--config -` reads the config from stdin; there is no file for `reload` then
+    let from_stdin = config_path.as_os_str() == "-";
 
-    // Immediately trigger instants at startup
-    {
-        let mut mgr = manager.lock().await;
-        mgr.trigger_instant_actions().await;
-    }
-    
-    // --- Spawn background tasks ---
-    let idle_handle = spawn_idle_task(Arc::clone(&manager));
-    let lock_handle = spawn_lock_watcher(Arc::clone(&manager)).await;
-    let input_handle = spawn_input_task(Arc::clone(&manager));
-    
-    // Store handles in manager
-    {
-        let mut mgr = manager.lock().await;
-        mgr.idle_task_handle = Some(idle_handle);
-        mgr.lock_task_handle = Some(lock_handle);
-        mgr.input_task_handle = Some(input_handle);
-    } 
-    
-    // --- Spawn suspend event listener ---
-    let dbus_manager = Arc::clone(&manager);
-    tokio::spawn(async move {
-        if let Err(e) = listen_for_power_events(dbus_manager).await {
-            log_error_message(&format!("D-Bus suspend event listener failed: {}", e));
-        }
-    });
-    
-    // --- AC/Battery Detection ---
-    let laptop_manager = Arc::clone(&manager);
-    tokio::spawn(spawn_power_source_monitor(laptop_manager));
-
-   // --- Spawn app inhibit task ---
-    let app_inhibitor = spawn_app_inhibit_task(
-        Arc::clone(&manager),
-        Arc::clone(&cfg)
-    ).await;
-   
-    // --- Spawn media monitor task ---
-    if cfg.monitor_media {
-        if let Err(e) = spawn_media_monitor_dbus(Arc::clone(&manager), cfg.ignore_remote_media).await {
-            log_error_message(&format!("Failed to spawn media monitor: {}", e));
-        }
-    }
-    
-    // --- Wayland setup ---
-    let wayland_manager = Arc::clone(&manager);
-    let _ = setup_wayland(wayland_manager, cfg.respect_wayland_inhibitors).await?;
-
-    // -- IPC Control Socket ---
-    ipc::spawn_ipc_socket_with_listener(
-        Arc::clone(&manager),
-        Arc::clone(&app_inhibitor),
-        config_path.to_str().unwrap().to_string(),
-        listener,
-    ).await;
-
-    setup_shutdown_handler(
-        Arc::clone(&manager),
-        Arc::clone(&app_inhibitor),
-    ).await;
-
-   // Monitor Wayland compositor connection
-    spawn_wayland_monitor(
-        Arc::clone(&manager),
-        Arc::clone(&app_inhibitor),
-    ).await;
-    
-    // --- Log startup message ---
-    log_message(&format!("Running. Idle actions loaded: {}", cfg.actions.len()));
+    // Log level precedence: CLI > STASIS_LOG > config
+    let level = cli_level
+        .or_else(env_log_level)
+        .or(cfg.log_level)
+        .unwrap_or(LogLevel::Info);
+    set_log_level(level);
+    let _handle = run(
+        cfg,
+        RunOptions {
+            config_path: (!from_stdin).then_some(config_path),
+            control_socket: Some(listener),
+            handle_signals: true,
+            exit_with_compositor: true,
+            soft_suspend_secs: args.debug_soft_suspend,
+        },
+    ).await?;
     
     // --- Run main async tasks ---
     let local = LocalSet::new();
@@ -248,79 +271,3 @@ async fn main() -> Result<()> {
     
     Ok(())
 }
-
-/// Async shutdown handler (Ctrl+C / SIGTERM)
-async fn setup_shutdown_handler(
-    idle_timer: Arc<Mutex<Manager>>,
-    app_inhibitor: Arc<Mutex<AppInhibitor>>,
-) {
-    let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt()).unwrap();
-    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).unwrap();
-    let mut sighup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).unwrap();
-
-    tokio::spawn({
-        let manager = Arc::clone(&idle_timer);
-        let app_inhibitor = Arc::clone(&app_inhibitor);
-        async move {
-            tokio::select! {
-                _ = sigint.recv() => {
-                    log_message("Received SIGINT, shutting down...");
-                },
-                _ = sigterm.recv() => {
-                    log_message("Received SIGTERM, shutting down...");
-                },
-                _ = sighup.recv() => {
-                    log_message("Received SIGHUP, shutting down...");
-                },
-            }
-
-            // Shutdown idle timer
-            manager.lock().await.shutdown().await;
-
-            // Shutdown app inhibitor
-            app_inhibitor.lock().await.shutdown().await;
-
-            let _ = std::fs::remove_file(SOCKET_PATH);
-            log_message("Shutdown complete, goodbye!");
-            std::process::exit(0);
-        }
-    });
-}
-
-async fn spawn_wayland_monitor(
-    manager: Arc<Mutex<Manager>>,
-    app_inhibitor: Arc<Mutex<AppInhibitor>>,
-) {
-    tokio::spawn(async move {
-        let wayland_display = match var("WAYLAND_DISPLAY") {
-            Ok(display) => display,
-            Err(_) => return,
-        };
-        
-        let xdg_runtime = match var("XDG_RUNTIME_DIR") {
-            Ok(dir) => dir,
-            Err(_) => "/run/user/1000".to_string(),
-        };
-        
-        let socket_path = format!("{}/{}", xdg_runtime, wayland_display);
-        
-        loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-            
-            // Check if Wayland socket still exists
-            if !std::path::Path::new(&socket_path).exists() {
-                log_message("Wayland compositor socket disappeared, shutting down...");
-                
-                // Shutdown idle timer
-                manager.lock().await.shutdown().await;
-                
-                // Shutdown app inhibitor
-                app_inhibitor.lock().await.shutdown().await;
-                
-                let _ = std::fs::remove_file(SOCKET_PATH);
-                log_message("Shutdown complete, goodbye!");
-                std::process::exit(0);
-            }
-        }
-    });
-}